@@ -1,13 +1,13 @@
-use std::{
-    any::{Any, TypeId},
-    collections::HashMap,
-    sync::{RwLock, RwLockReadGuard},
-};
+use std::collections::HashMap;
 
-use log::debug;
 use thiserror::Error;
 
-use super::entities::{EntityArchetypeIndex, EntityId};
+use super::{
+    change_detection::{ComponentTicks, Tick},
+    column::Column,
+    components::{Components, StorageType},
+    entities::{EntityArchetypeIndex, EntityId},
+};
 
 #[derive(Debug, Error)]
 pub enum ArchetypeError {
@@ -23,112 +23,110 @@ pub type ArchetypeId = usize;
 
 pub type ComponentId = usize;
 
-pub trait Component: 'static {}
+pub trait Component: Send + Sync + 'static {}
 
-impl<T: 'static> Component for T {}
+impl<T: Send + Sync + 'static> Component for T {}
 
-pub trait ComponentSet {
-    fn to_any(&self) -> &dyn Any;
-    fn to_any_mut(&mut self) -> &mut dyn Any;
-    fn len(&self) -> usize;
-    fn remove(&mut self, index: usize);
-    fn expand(&mut self);
-    fn empty_clone(&self) -> Box<dyn ComponentSet>;
-    fn migrate(&mut self, index: usize, other_set: &mut dyn ComponentSet);
+#[derive(Default)]
+pub struct Archetype {
+    /// Sorted ids of the components of the archetype's entities, the archetype's signature.
+    component_ids: Vec<ComponentId>,
+    /// One column per component, in the same order as 'component_ids'.
+    /// Tags aren't stored, so they don't get one.
+    columns: Vec<Column>,
+    /// Column of each component, indexed by 'ComponentId'.
+    column_indices: Vec<Option<usize>>,
+    pub entities: Vec<EntityId>,
+    /// Archetype reached by adding a component, filled in on the first transition.
+    pub(crate) add_edges: HashMap<ComponentId, ArchetypeId>,
+    /// Archetype reached by removing a component, filled in on the first transition.
+    pub(crate) remove_edges: HashMap<ComponentId, ArchetypeId>,
 }
 
-pub type Lock<T> = RwLock<T>;
-
-impl<T: Component> ComponentSet for Lock<Vec<T>> {
-    fn to_any(&self) -> &dyn Any {
-        self
-    }
-    fn to_any_mut(&mut self) -> &mut dyn Any {
-        self
-    }
-    fn len(&self) -> usize {
-        self.read().unwrap().len()
-    }
-    fn remove(&mut self, index: usize) {
-        self.get_mut().unwrap().swap_remove(index);
-    }
-    fn expand(&mut self) {
-        self.write().unwrap().reserve(1);
-    }
-    fn empty_clone(&self) -> Box<dyn ComponentSet> {
-        Box::<Lock<Vec<T>>>::default()
-    }
-    fn migrate(&mut self, index: usize, other_set: &mut dyn ComponentSet) {
-        let data: T = { self.get_mut().unwrap().swap_remove(index) };
-        component_set_to_mut(other_set).push(data);
+impl Archetype {
+    /// 'component_ids' must be sorted.
+    pub(crate) fn new(component_ids: Vec<ComponentId>, components: &Components) -> Self {
+        let columns = component_ids
+            .iter()
+            .map(|component_id| components.info(*component_id))
+            .filter(|info| !info.is_tag())
+            .map(Column::new)
+            .collect::<Vec<_>>();
+        let mut column_indices = vec![None; component_ids.last().map_or(0, |id| id + 1)];
+        for (index, column) in columns.iter().enumerate() {
+            column_indices[column.component_id()] = Some(index);
+        }
+        Self {
+            component_ids,
+            columns,
+            column_indices,
+            ..Default::default()
+        }
     }
-}
 
-pub struct ComponentStore {
-    pub type_id: TypeId,
-    pub data: Box<dyn ComponentSet>,
-}
+    pub fn component_ids(&self) -> &[ComponentId] {
+        &self.component_ids
+    }
 
-impl ComponentStore {
-    pub fn new<T: Component>() -> Self {
-        Self {
-            type_id: TypeId::of::<T>(),
-            data: Box::<Lock<Vec<T>>>::default(),
-        }
+    pub(crate) fn has_component_id(&self, component_id: ComponentId) -> bool {
+        self.component_ids.binary_search(&component_id).is_ok()
     }
-    pub fn empty_clone(&self) -> ComponentStore {
-        ComponentStore {
-            type_id: self.type_id,
-            data: self.data.empty_clone(),
-        }
+
+    pub(crate) fn has_component<T: Component>(&self, components: &Components) -> bool {
+        components
+            .id::<T>()
+            .is_some_and(|component_id| self.has_component_id(component_id))
     }
-}
 
-// This could be made unchecked in the future if there's a high degree of confidence in everything else.
-fn component_set_to_mut<T: 'static>(c: &mut dyn ComponentSet) -> &mut Vec<T> {
-    c.to_any_mut()
-        .downcast_mut::<Lock<Vec<T>>>()
-        .unwrap()
-        .get_mut()
-        .unwrap()
-}
+    /// Whether 'T' is one of this archetype's tags, which have no column.
+    pub(crate) fn has_tag<T: Component>(&self, components: &Components) -> bool {
+        components.id::<T>().is_some_and(|component_id| {
+            self.has_component_id(component_id) && self.column_index(component_id).is_none()
+        })
+    }
 
-// This could be made unchecked in the future if there's a high degree of confidence in everything else.
-fn component_set_to_ref<T: 'static>(c: &dyn ComponentSet) -> RwLockReadGuard<'_, Vec<T>> {
-    c.to_any()
-        .downcast_ref::<Lock<Vec<T>>>()
-        .unwrap()
-        .try_read()
-        .unwrap()
-}
+    /// Whether entities of this archetype may have a 'T'.
+    /// Sparse components aren't part of archetypes, so any entity may have one.
+    pub(crate) fn may_have_component<T: Component>(&self, components: &Components) -> bool {
+        components.id::<T>().is_some_and(|component_id| {
+            components.info(component_id).storage_type() == StorageType::SparseSet
+                || self.has_component_id(component_id)
+        })
+    }
 
-#[derive(Default)]
-pub struct Archetype {
-    pub components: HashMap<TypeId, ComponentStore>,
-    pub entities: Vec<EntityId>,
-}
+    fn column_index(&self, component_id: ComponentId) -> Option<usize> {
+        self.column_indices.get(component_id).copied().flatten()
+    }
 
-impl Archetype {
-    /// Gets ComponentSet through its TypeId, downcasts to &mut Vec<T>.
-    pub(crate) fn get_component_set_mut<T: Component>(&mut self) -> &mut Vec<T> {
-        component_set_to_mut(&mut *self.components.get_mut(&TypeId::of::<T>()).unwrap().data)
+    pub(crate) fn get_column(&self, component_id: ComponentId) -> Option<&Column> {
+        Some(&self.columns[self.column_index(component_id)?])
     }
 
-    pub(crate) fn has_component<T: Component>(&self) -> bool {
-        self.components.get(&TypeId::of::<T>()).is_some()
+    pub(crate) fn get_column_mut(&mut self, component_id: ComponentId) -> Option<&mut Column> {
+        let index = self.column_index(component_id)?;
+        Some(&mut self.columns[index])
     }
 
     /// Should be used to add components for a newly added entity.
-    pub(crate) fn add_entity_component<T: Component>(&mut self, component: T) {
-        self.get_component_set_mut::<T>().push(component)
+    pub(crate) fn add_entity_component<T: Component>(
+        &mut self,
+        component_id: ComponentId,
+        component: T,
+        tick: Tick,
+    ) {
+        match self.get_column_mut(component_id) {
+            Some(column) => column.push(component, ComponentTicks::new(tick)),
+            // Tags aren't stored
+            None => debug_assert!(self.has_component_id(component_id)),
+        }
     }
 
     /// Add entity to archetype.
     pub(crate) fn add_entity(&mut self, entity_id: EntityId) -> EntityArchetypeIndex {
         let index = self.entities.len();
         self.entities.push(entity_id);
-        for (_, comp_store) in self.components.iter_mut() {
-            comp_store.data.expand();
+        for column in self.columns.iter_mut() {
+            column.reserve(1);
         }
         index
     }
@@ -148,35 +146,68 @@ impl Archetype {
         moved
     }
 
+    /// Removes the entity and drops all of its components, returns moved entity.
+    pub(crate) fn remove_entity_with_components(
+        &mut self,
+        index_in_archetype: EntityArchetypeIndex,
+    ) -> Option<EntityId> {
+        for column in self.columns.iter_mut() {
+            column.swap_remove(index_in_archetype);
+        }
+        self.remove_entity(index_in_archetype)
+    }
+
     pub(crate) fn set_entity_component<T: Component>(
         &mut self,
+        component_id: ComponentId,
         index_in_archetype: EntityArchetypeIndex,
         comp: T,
+        tick: Tick,
     ) -> Result<(), ArchetypeError> {
-        let comp_store = self.get_component_set_mut::<T>();
-        if index_in_archetype >= comp_store.len() {
+        if index_in_archetype >= self.entities.len() {
             return Err(ArchetypeError::UnderCapacity);
         }
-        let c = comp_store.get_mut(index_in_archetype).unwrap();
-        *c = comp;
+        let Some(column) = self.get_column_mut(component_id) else {
+            // Tags have nothing to overwrite
+            if self.has_component_id(component_id) {
+                return Ok(());
+            }
+            return Err(ArchetypeError::ComponentMissing);
+        };
+        column.get_mut::<T>()[index_in_archetype] = comp;
+        column.ticks[index_in_archetype].set_changed(tick);
         Ok(())
     }
 
-    pub(crate) fn get_entity_component<T: Component>(&self) -> RwLockReadGuard<'_, Vec<T>> {
-        component_set_to_ref(&*self.components.get(&TypeId::of::<T>()).unwrap().data)
+    /// Moves the components of an entity to 'other', dropping the ones 'other' doesn't have.
+    /// The entity itself has to be removed with 'remove_entity' afterwards.
+    pub(crate) fn migrate_entity(
+        &mut self,
+        index_in_archetype: EntityArchetypeIndex,
+        other_archetype: &mut Archetype,
+    ) {
+        for column in self.columns.iter_mut() {
+            match other_archetype.get_column_mut(column.component_id()) {
+                Some(other_column) => column.migrate(index_in_archetype, other_column),
+                None => column.swap_remove(index_in_archetype),
+            }
+        }
     }
 
-    pub(crate) fn migrate_component(
+    /// Like 'migrate_entity', but the components 'other' doesn't have are forgotten instead of
+    /// dropped.
+    /// # Safety
+    /// Those components must have been moved out with 'Column::read' already.
+    pub(crate) unsafe fn migrate_entity_forgetting(
         &mut self,
-        type_id: TypeId,
         index_in_archetype: EntityArchetypeIndex,
         other_archetype: &mut Archetype,
     ) {
-        let other_set = &mut *other_archetype.components.get_mut(&type_id).unwrap().data;
-        self.components
-            .get_mut(&type_id)
-            .expect("")
-            .data
-            .migrate(index_in_archetype, other_set)
+        for column in self.columns.iter_mut() {
+            match other_archetype.get_column_mut(column.component_id()) {
+                Some(other_column) => column.migrate(index_in_archetype, other_column),
+                None => column.swap_remove_forget(index_in_archetype),
+            }
+        }
     }
 }
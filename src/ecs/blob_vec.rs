@@ -0,0 +1,174 @@
+use std::{
+    alloc::{alloc, dealloc, handle_alloc_error, realloc, Layout},
+    ptr::{self, NonNull},
+};
+
+/// A type-erased 'Vec', storing items of a single layout back to back.
+/// Items are moved in and out as raw bytes, so it's up to the owner to only put in and take
+/// out values of the type the layout was made from.
+pub(crate) struct BlobVec {
+    item_layout: Layout,
+    drop: Option<unsafe fn(*mut u8)>,
+    data: NonNull<u8>,
+    len: usize,
+    capacity: usize,
+}
+
+// Safety: only components are stored, which are 'Send + Sync'
+unsafe impl Send for BlobVec {}
+unsafe impl Sync for BlobVec {}
+
+impl BlobVec {
+    pub(crate) fn new(item_layout: Layout, drop: Option<unsafe fn(*mut u8)>) -> Self {
+        // Zero-sized items never need an allocation
+        let capacity = if item_layout.size() == 0 {
+            usize::MAX
+        } else {
+            0
+        };
+        Self {
+            item_layout,
+            drop,
+            data: NonNull::new(ptr::without_provenance_mut(item_layout.align())).unwrap(),
+            len: 0,
+            capacity,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn item_layout(&self) -> Layout {
+        self.item_layout
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        let needed = self
+            .len
+            .checked_add(additional)
+            .expect("BlobVec capacity overflow");
+        if needed <= self.capacity {
+            return;
+        }
+        let new_capacity = needed.max(self.capacity * 2).max(4);
+        let new_layout = array_layout(self.item_layout, new_capacity);
+        // Safety: the layout isn't zero-sized, as zero-sized items have a capacity of usize::MAX
+        let data = unsafe {
+            if self.capacity == 0 {
+                alloc(new_layout)
+            } else {
+                realloc(
+                    self.data.as_ptr(),
+                    array_layout(self.item_layout, self.capacity),
+                    new_layout.size(),
+                )
+            }
+        };
+        self.data = NonNull::new(data).unwrap_or_else(|| handle_alloc_error(new_layout));
+        self.capacity = new_capacity;
+    }
+
+    /// Pointer to the first item.
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        self.data.as_ptr()
+    }
+
+    /// # Safety
+    /// 'index' must be at most 'capacity'.
+    unsafe fn get_ptr(&self, index: usize) -> *mut u8 {
+        self.data.as_ptr().add(index * self.item_layout.size())
+    }
+
+    /// Copies an item in at the end.
+    /// # Safety
+    /// 'value' must point to a valid item of this vec's layout, which the caller must not use or
+    /// drop afterwards.
+    pub(crate) unsafe fn push(&mut self, value: *const u8) {
+        self.reserve(1);
+        ptr::copy_nonoverlapping(value, self.get_ptr(self.len), self.item_layout.size());
+        self.len += 1;
+    }
+
+    /// Removes the item at 'index' without dropping it, moving the last item into its place.
+    /// # Safety
+    /// 'index' must be in bounds and the item must have been moved out already.
+    pub(crate) unsafe fn swap_remove_forget(&mut self, index: usize) {
+        debug_assert!(index < self.len);
+        let last = self.len - 1;
+        if index != last {
+            ptr::copy_nonoverlapping(
+                self.get_ptr(last),
+                self.get_ptr(index),
+                self.item_layout.size(),
+            );
+        }
+        self.len = last;
+    }
+
+    /// Moves the item at 'index' to the end of 'other', moving the last item into its place.
+    /// # Safety
+    /// 'other' must store items of the same type.
+    pub(crate) unsafe fn swap_remove_into(&mut self, index: usize, other: &mut BlobVec) {
+        assert!(index < self.len);
+        debug_assert_eq!(self.item_layout, other.item_layout);
+        other.push(self.get_ptr(index));
+        self.swap_remove_forget(index);
+    }
+
+    /// Drops the item at 'index', moving the last item into its place.
+    pub(crate) fn swap_remove_and_drop(&mut self, index: usize) {
+        assert!(index < self.len);
+        let last = self.len - 1;
+        // Safety: both indices are in bounds, the length is updated before dropping so a
+        // panicking drop can't cause a double drop
+        unsafe {
+            if index != last {
+                ptr::swap_nonoverlapping(
+                    self.get_ptr(index),
+                    self.get_ptr(last),
+                    self.item_layout.size(),
+                );
+            }
+            self.len = last;
+            if let Some(drop) = self.drop {
+                drop(self.get_ptr(last));
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        let len = self.len;
+        self.len = 0;
+        if let Some(drop) = self.drop {
+            for index in 0..len {
+                // Safety: every index below the old length holds a valid item
+                unsafe { drop(self.get_ptr(index)) }
+            }
+        }
+    }
+}
+
+impl Drop for BlobVec {
+    fn drop(&mut self) {
+        self.clear();
+        if self.item_layout.size() != 0 && self.capacity > 0 {
+            // Safety: the data was allocated with this layout in 'reserve'
+            unsafe {
+                dealloc(
+                    self.data.as_ptr(),
+                    array_layout(self.item_layout, self.capacity),
+                )
+            }
+        }
+    }
+}
+
+fn array_layout(item_layout: Layout, len: usize) -> Layout {
+    // Rust layouts are always padded to their alignment, so items can be laid out back to back
+    let size = item_layout
+        .size()
+        .checked_mul(len)
+        .expect("BlobVec capacity overflow");
+    Layout::from_size_align(size, item_layout.align()).expect("BlobVec capacity overflow")
+}
@@ -1,68 +1,94 @@
-use std::{
-    any::TypeId,
-    collections::hash_map::DefaultHasher,
-    hash::{Hash, Hasher},
-};
-
 use super::{
-    archetype::{Archetype, Component, ComponentStore},
+    archetype::{Archetype, Component, ComponentId},
+    components::Components,
     entities::{EntityId, EntityLocation},
     world::World,
 };
 
-pub(crate) type BundleId = u64;
+/// The sorted component ids of a bundle's components.
+/// Archetypes are looked up by comparing the whole signature, so two bundles can only share
+/// an archetype if they have the exact same components.
+pub(crate) type BundleId = Box<[ComponentId]>;
 
 pub trait ComponentBundle: 'static {
-    fn new_archetype(&self) -> Archetype;
     fn spawn_in_world(self, world: &mut World, entity_id: EntityId) -> EntityLocation;
-}
 
-pub fn calculate_bundle_id(types: &[TypeId]) -> u64 {
-    let mut s = DefaultHasher::new();
-    types.hash(&mut s);
-    s.finish()
+    /// Ids of the bundle's components in tuple order, registering them if needed.
+    fn component_ids(components: &mut Components) -> Vec<ComponentId>;
+
+    /// Store the components of an entity that was already moved to an archetype with all of
+    /// them. 'added' tells, in tuple order, which ones the entity didn't have before.
+    fn insert_in_world(
+        self,
+        world: &mut World,
+        entity_id: EntityId,
+        location: EntityLocation,
+        added: &[bool],
+    );
+
+    /// Move the components out of the entity at 'location'.
+    /// # Safety
+    /// See 'World::take_component_at'.
+    unsafe fn take_from_world(world: &mut World, location: EntityLocation) -> Self;
 }
 
 macro_rules! component_bundle_impl {
     ($($name:tt $index:tt),*) => {
         impl<$($name: Component),*> ComponentBundle for ($($name,)*) {
-            fn new_archetype(&self) -> Archetype {
-                let mut components = vec![$(ComponentStore::new::<$name>()),*];
-                components.sort_unstable_by(|a, b| a.type_id.cmp(&b.type_id));
-                Archetype {
-                    components: components
-                        .into_iter()
-                        .map(|comp_store| (comp_store.type_id, comp_store))
-                        .collect(),
-                    entities: Vec::new(),
-                }
-            }
-
             fn spawn_in_world(self, world: &mut World, entity_id: EntityId) -> EntityLocation {
-                let mut types = [$(($index, TypeId::of::<$name>())),*];
-                types.sort_unstable_by(|a, b| a.1.cmp(&b.1));
+                let ids = [$(world.components_mut().register::<$name>()),*];
+                let mut sorted_ids = ids;
+                sorted_ids.sort_unstable();
                 debug_assert!(
-                    types.windows(2).all(|x| x[0].1 != x[1].1),
+                    sorted_ids.windows(2).all(|x| x[0] != x[1]),
                     "'ComponentBundles' can't have duplicate types"
                 );
-                let types = [$(types[$index].1),*];
-                let bundle_id = calculate_bundle_id(&types);
-                let archetype_id = if let Some(id) = world.get_bundle_archetype(bundle_id) {
+                // Sparse components aren't part of the archetype
+                let sorted_ids = sorted_ids
+                    .into_iter()
+                    .filter(|id| world.sparse_set(*id).is_none())
+                    .collect::<Vec<_>>();
+                let archetype_id = if let Some(id) = world.get_bundle_archetype(&sorted_ids) {
                     *id
                 } else {
-                    let archetype = self.new_archetype();
+                    let archetype = Archetype::new(sorted_ids.clone(), world.components());
                     let id = world.next_archetype_id();
-                    world.set_bundle_archetype(bundle_id, id);
+                    world.set_bundle_archetype(sorted_ids.into(), id);
                     world.add_archetype(archetype);
                     id
                 };
                 let index_in_archetype = world.add_entity_to_archetype(archetype_id, entity_id);
-                $(world.add_component_to_archetype(archetype_id, self.$index);)*
+                $(world.add_spawned_component(archetype_id, entity_id, ids[$index], self.$index);)*
                 EntityLocation {
                     archetype_id,
                     index_in_archetype,
                 }
             }
+
+            fn component_ids(components: &mut Components) -> Vec<ComponentId> {
+                let ids = vec![$(components.register::<$name>()),*];
+                // Moving components in or out twice would corrupt storage, so this is checked
+                // even in release builds
+                assert!(
+                    ids.iter().enumerate().all(|(index, id)| !ids[..index].contains(id)),
+                    "'ComponentBundles' can't have duplicate types"
+                );
+                ids
+            }
+
+            fn insert_in_world(
+                self,
+                world: &mut World,
+                entity_id: EntityId,
+                location: EntityLocation,
+                added: &[bool],
+            ) {
+                $(world.insert_component_at(entity_id, location, added[$index], self.$index);)*
+            }
+
+            unsafe fn take_from_world(world: &mut World, location: EntityLocation) -> Self {
+                ($(world.take_component_at::<$name>(location),)*)
+            }
         }
     };
 }
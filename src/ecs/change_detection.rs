@@ -0,0 +1,130 @@
+use std::{
+    ops::{Deref, DerefMut},
+    ptr,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+/// World time, advanced every time a system runs.
+pub type Tick = u32;
+
+/// The ticks a system compares component ticks against.
+/// 'last_run' is when the system last ran, 'this_run' is the tick its writes are stamped with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemTicks {
+    pub last_run: Tick,
+    pub this_run: Tick,
+}
+
+impl SystemTicks {
+    /// Whether 'tick' happened after 'last_run', tolerating the world tick wrapping around.
+    pub fn is_newer(&self, tick: Tick) -> bool {
+        self.this_run.wrapping_sub(self.last_run) > self.this_run.wrapping_sub(tick)
+    }
+}
+
+/// When a component was added and last changed.
+/// Ticks are atomic so they can be stamped through shared borrows of a column, while other
+/// systems filter on them.
+#[derive(Debug)]
+pub struct ComponentTicks {
+    added: AtomicU32,
+    changed: AtomicU32,
+}
+
+/// Ticks handed out for every tag.
+/// Tags aren't stored anywhere, so they aren't tracked and never count as added or changed.
+/// 'Added' and 'Changed' filters reject them instead of reading these.
+pub(crate) static TAG_TICKS: ComponentTicks = ComponentTicks {
+    added: AtomicU32::new(0),
+    changed: AtomicU32::new(0),
+};
+
+impl ComponentTicks {
+    pub(crate) fn new(tick: Tick) -> Self {
+        Self {
+            added: AtomicU32::new(tick),
+            changed: AtomicU32::new(tick),
+        }
+    }
+
+    pub fn added(&self) -> Tick {
+        self.added.load(Ordering::Relaxed)
+    }
+
+    pub fn changed(&self) -> Tick {
+        self.changed.load(Ordering::Relaxed)
+    }
+
+    /// Tags all share 'TAG_TICKS', which stay untouched.
+    pub(crate) fn set_changed(&self, tick: Tick) {
+        if !ptr::eq(self, &TAG_TICKS) {
+            self.changed.store(tick, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_added(&self, system_ticks: SystemTicks) -> bool {
+        system_ticks.is_newer(self.added())
+    }
+
+    pub fn is_changed(&self, system_ticks: SystemTicks) -> bool {
+        system_ticks.is_newer(self.changed())
+    }
+}
+
+/// Shared access to a component that knows whether it was added or changed since the
+/// system last ran.
+pub struct Ref<'a, T> {
+    pub(crate) value: &'a T,
+    pub(crate) ticks: &'a ComponentTicks,
+    pub(crate) system_ticks: SystemTicks,
+}
+
+impl<T> Ref<'_, T> {
+    pub fn is_added(&self) -> bool {
+        self.ticks.is_added(self.system_ticks)
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.ticks.is_changed(self.system_ticks)
+    }
+}
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+/// Exclusive access to a component, mutably dereferencing it marks it as changed.
+pub struct Mut<'a, T> {
+    pub(crate) value: &'a mut T,
+    pub(crate) ticks: &'a ComponentTicks,
+    pub(crate) system_ticks: SystemTicks,
+}
+
+impl<T> Mut<'_, T> {
+    pub fn is_added(&self) -> bool {
+        self.ticks.is_added(self.system_ticks)
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.ticks.is_changed(self.system_ticks)
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.ticks.set_changed(self.system_ticks.this_run);
+        self.value
+    }
+}
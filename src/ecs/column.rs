@@ -0,0 +1,340 @@
+use std::{
+    any::TypeId,
+    marker::PhantomData,
+    mem::{size_of, ManuallyDrop},
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::{
+    archetype::{Component, ComponentId},
+    blob_vec::BlobVec,
+    change_detection::ComponentTicks,
+    components::ComponentInfo,
+};
+
+const WRITING: usize = usize::MAX;
+
+/// Runtime borrow state of a column.
+/// Like a 'RwLock', except borrows fail right away instead of blocking.
+#[derive(Default)]
+struct BorrowFlag(AtomicUsize);
+
+impl BorrowFlag {
+    fn try_borrow(&self) -> bool {
+        let mut current = self.0.load(Ordering::Relaxed);
+        loop {
+            if current >= WRITING - 1 {
+                return false;
+            }
+            match self.0.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    fn try_borrow_mut(&self) -> bool {
+        self.0
+            .compare_exchange(0, WRITING, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    fn release(&self) {
+        self.0.fetch_sub(1, Ordering::Release);
+    }
+
+    fn release_mut(&self) {
+        self.0.store(0, Ordering::Release);
+    }
+}
+
+/// Values of a single component type in an archetype, with the ticks of each value alongside.
+/// Typed accessors check the type they're called with, like a downcast would.
+pub struct Column {
+    component_id: ComponentId,
+    type_id: TypeId,
+    data: BlobVec,
+    pub(crate) ticks: Vec<ComponentTicks>,
+    borrow: BorrowFlag,
+}
+
+impl Column {
+    pub(crate) fn new(info: &ComponentInfo) -> Self {
+        Self {
+            component_id: info.id(),
+            type_id: info.type_id(),
+            data: BlobVec::new(info.layout(), info.drop()),
+            ticks: Vec::new(),
+            borrow: BorrowFlag::default(),
+        }
+    }
+
+    pub fn component_id(&self) -> ComponentId {
+        self.component_id
+    }
+
+    pub fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    fn check_type<T: Component>(&self) {
+        assert_eq!(
+            TypeId::of::<T>(),
+            self.type_id,
+            "Column accessed as the wrong component type"
+        );
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.data.reserve(additional);
+        self.ticks.reserve(additional);
+    }
+
+    pub(crate) fn push<T: Component>(&mut self, component: T, ticks: ComponentTicks) {
+        self.check_type::<T>();
+        let component = ManuallyDrop::new(component);
+        // Safety: the type was checked, and the value is forgotten once copied in
+        unsafe { self.data.push(&*component as *const T as *const u8) };
+        self.ticks.push(ticks);
+    }
+
+    /// Drops the value at 'index', moving the last one into its place.
+    pub(crate) fn swap_remove(&mut self, index: usize) {
+        self.data.swap_remove_and_drop(index);
+        self.ticks.swap_remove(index);
+    }
+
+    /// Moves the value at 'index' out, leaving its row in place.
+    /// # Safety
+    /// The row must be removed with 'swap_remove_forget' before the column is used again.
+    pub(crate) unsafe fn read<T: Component>(&self, index: usize) -> T {
+        self.check_type::<T>();
+        assert!(index < self.len());
+        self.data.as_ptr().cast::<T>().add(index).read()
+    }
+
+    /// Removes the row at 'index' without dropping its value, moving the last one into its place.
+    /// # Safety
+    /// The value must have been moved out with 'read' already.
+    pub(crate) unsafe fn swap_remove_forget(&mut self, index: usize) {
+        self.data.swap_remove_forget(index);
+        self.ticks.swap_remove(index);
+    }
+
+    /// Moves the value at 'index' to the end of 'other', as raw bytes.
+    pub(crate) fn migrate(&mut self, index: usize, other: &mut Column) {
+        assert_eq!(self.type_id, other.type_id);
+        // Safety: both columns store the same type
+        unsafe { self.data.swap_remove_into(index, &mut other.data) };
+        other.ticks.push(self.ticks.swap_remove(index));
+    }
+
+    /// Exclusive access without going through the borrow flag, the column is borrowed mutably.
+    pub(crate) fn get_mut<T: Component>(&mut self) -> &mut [T] {
+        self.check_type::<T>();
+        // Safety: the type was checked and every index below 'len' holds a valid value
+        unsafe { std::slice::from_raw_parts_mut(self.data.as_ptr().cast::<T>(), self.data.len()) }
+    }
+
+    /// Run 'f' on a pointer to the value at 'index', for callers that don't know its type.
+    /// Returns None if the column is mutably borrowed.
+    pub(crate) fn try_read_raw<R>(
+        &self,
+        index: usize,
+        f: impl FnOnce(*const u8) -> R,
+    ) -> Option<R> {
+        assert!(index < self.len());
+        if !self.borrow.try_borrow() {
+            return None;
+        }
+        let result = f(self
+            .data
+            .as_ptr()
+            .wrapping_add(index * self.data.item_layout().size()));
+        self.borrow.release();
+        Some(result)
+    }
+
+    pub fn try_read<T: Component>(&self) -> Option<ColumnRef<'_, T>> {
+        if !self.borrow.try_borrow() {
+            return None;
+        }
+        // Safety: the flag guarantees nobody is writing to the column
+        let mut column = unsafe { self.read_unguarded() };
+        column.borrow = Some(&self.borrow);
+        Some(column)
+    }
+
+    pub fn try_write<T: Component>(&self) -> Option<ColumnMut<'_, T>> {
+        if !self.borrow.try_borrow_mut() {
+            return None;
+        }
+        // Safety: the flag guarantees nobody else is reading or writing the column
+        let mut column = unsafe { self.write_unguarded() };
+        column.borrow = Some(&self.borrow);
+        Some(column)
+    }
+
+    /// Borrow the column without picking a type, so it can be shared by several unguarded
+    /// accesses.
+    pub(crate) fn try_lock(&self, write: bool) -> Option<ColumnLock<'_>> {
+        let locked = if write {
+            self.borrow.try_borrow_mut()
+        } else {
+            self.borrow.try_borrow()
+        };
+        locked.then_some(ColumnLock {
+            borrow: &self.borrow,
+            write,
+        })
+    }
+
+    /// Like 'try_read', without going through the borrow flag.
+    /// # Safety
+    /// The caller must hold a 'ColumnLock' of the column for as long as the result lives.
+    pub(crate) unsafe fn read_unguarded<T: Component>(&self) -> ColumnRef<'_, T> {
+        self.check_type::<T>();
+        ColumnRef {
+            data: std::slice::from_raw_parts(self.data.as_ptr().cast::<T>(), self.data.len()),
+            borrow: None,
+        }
+    }
+
+    /// Like 'try_write', without going through the borrow flag.
+    /// # Safety
+    /// The caller must hold a write 'ColumnLock' of the column for as long as the result lives,
+    /// and unguarded borrows sharing it must not access the same values.
+    pub(crate) unsafe fn write_unguarded<T: Component>(&self) -> ColumnMut<'_, T> {
+        self.check_type::<T>();
+        // Values live in their own allocation so the shared borrow of the column doesn't
+        // cover them
+        ColumnMut {
+            data: NonNull::new_unchecked(self.data.as_ptr().cast::<T>()),
+            len: self.data.len(),
+            borrow: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Borrow of a whole column, released when dropped.
+pub struct ColumnLock<'a> {
+    borrow: &'a BorrowFlag,
+    write: bool,
+}
+
+impl Drop for ColumnLock<'_> {
+    fn drop(&mut self) {
+        if self.write {
+            self.borrow.release_mut();
+        } else {
+            self.borrow.release();
+        }
+    }
+}
+
+impl<T: Component> ColumnRef<'static, T> {
+    /// Values of a tag, which aren't stored anywhere.
+    pub(crate) fn tag(len: usize) -> Self {
+        assert_eq!(size_of::<T>(), 0, "Only zero-sized components can be tags");
+        ColumnRef {
+            // Safety: zero-sized values can be read from any aligned pointer
+            data: unsafe { std::slice::from_raw_parts(NonNull::dangling().as_ptr(), len) },
+            borrow: None,
+        }
+    }
+}
+
+impl<T: Component> ColumnMut<'static, T> {
+    /// Values of a tag, which aren't stored anywhere.
+    pub(crate) fn tag(len: usize) -> Self {
+        assert_eq!(size_of::<T>(), 0, "Only zero-sized components can be tags");
+        ColumnMut {
+            data: NonNull::dangling(),
+            len,
+            borrow: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Shared borrow of a column's values, released when dropped.
+pub struct ColumnRef<'a, T> {
+    data: &'a [T],
+    /// None if the column is locked by someone else, see 'Column::read_unguarded'.
+    borrow: Option<&'a BorrowFlag>,
+}
+
+impl<T> Deref for ColumnRef<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.data
+    }
+}
+
+impl<T> Drop for ColumnRef<'_, T> {
+    fn drop(&mut self) {
+        if let Some(borrow) = self.borrow {
+            borrow.release();
+        }
+    }
+}
+
+/// Exclusive borrow of a column's values, released when dropped.
+/// Values are kept behind a pointer, so unguarded borrows of the same column don't alias until
+/// they're dereferenced.
+pub struct ColumnMut<'a, T> {
+    data: NonNull<T>,
+    len: usize,
+    /// None if the column is locked by someone else, see 'Column::write_unguarded'.
+    borrow: Option<&'a BorrowFlag>,
+    _marker: PhantomData<&'a mut [T]>,
+}
+
+// Safety: it's used like a '&mut [T]'
+unsafe impl<T: Send> Send for ColumnMut<'_, T> {}
+unsafe impl<T: Sync> Sync for ColumnMut<'_, T> {}
+
+impl<T> ColumnMut<'_, T> {
+    /// Pointer to the first value, without borrowing the whole column.
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut T {
+        self.data.as_ptr()
+    }
+
+    /// Number of values, without borrowing the whole column.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<T> Deref for ColumnMut<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // Safety: the column is borrowed for 'a and holds 'len' values
+        unsafe { std::slice::from_raw_parts(self.data.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for ColumnMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        // Safety: the column is borrowed mutably for 'a and holds 'len' values
+        unsafe { std::slice::from_raw_parts_mut(self.data.as_ptr(), self.len) }
+    }
+}
+
+impl<T> Drop for ColumnMut<'_, T> {
+    fn drop(&mut self) {
+        if let Some(borrow) = self.borrow {
+            borrow.release_mut();
+        }
+    }
+}
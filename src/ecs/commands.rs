@@ -0,0 +1,105 @@
+use std::sync::Mutex;
+
+use super::{
+    archetype::Component,
+    bundles::ComponentBundle,
+    change_detection::SystemTicks,
+    entities::Entity,
+    systems::{Access, SystemParam, SystemParamFetch},
+    world::{EcsError, World},
+};
+
+/// A deferred change to the world, applied at the next sync point.
+pub type Command = Box<dyn FnOnce(&mut World) -> Result<(), EcsError> + Send>;
+
+/// Commands recorded by every 'Commands' handle, waiting for 'World::apply_commands'.
+#[derive(Default)]
+pub(crate) struct CommandQueue {
+    commands: Mutex<Vec<Command>>,
+}
+
+impl CommandQueue {
+    fn append(&self, commands: &mut Vec<Command>) {
+        self.commands.lock().unwrap().append(commands);
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<Command> {
+        std::mem::take(self.commands.get_mut().unwrap())
+    }
+}
+
+/// Records structural changes while the world is only borrowed, e.g. while a 'Query' is alive.
+/// Commands are queued when the handle is dropped, keeping each handle's commands together,
+/// and applied in 'World::update' after every system has run.
+pub struct Commands<'world_borrow> {
+    world: &'world_borrow World,
+    commands: Vec<Command>,
+}
+
+impl<'world_borrow> Commands<'world_borrow> {
+    pub(crate) fn new(world: &'world_borrow World) -> Self {
+        Self {
+            world,
+            commands: Vec::new(),
+        }
+    }
+
+    /// Queue a custom command.
+    pub fn add(
+        &mut self,
+        command: impl FnOnce(&mut World) -> Result<(), EcsError> + Send + 'static,
+    ) {
+        self.commands.push(Box::new(command));
+    }
+
+    /// Queue a spawn, the returned entity can be used right away but isn't live until the
+    /// command is applied.
+    pub fn spawn(&mut self, bundle: impl ComponentBundle + Send) -> Entity {
+        let entity = self.world.entities().reserve();
+        self.add(move |world| world.spawn_reserved(entity, bundle));
+        entity
+    }
+
+    /// Queue a despawn.
+    pub fn remove(&mut self, entity: Entity) {
+        self.add(move |world| world.remove(entity));
+    }
+
+    /// Queue adding or overwriting a component.
+    /// Fails with a warning if the entity was despawned by the time commands are applied.
+    pub fn add_component<T: Component>(&mut self, entity: Entity, component: T) {
+        self.add(move |world| world.add_component(entity, component));
+    }
+
+    /// Queue removing a component.
+    /// Fails with a warning if the entity was despawned by the time commands are applied.
+    pub fn remove_component<T: Component>(&mut self, entity: Entity) {
+        self.add(move |world| world.remove_component::<T>(entity).map(drop));
+    }
+}
+
+impl Drop for Commands<'_> {
+    fn drop(&mut self) {
+        self.world.command_queue().append(&mut self.commands);
+    }
+}
+
+#[derive(Default)]
+pub struct CommandsFetch;
+
+impl SystemParam for Commands<'_> {
+    type Fetch = CommandsFetch;
+    fn access(_access: &mut Access) {}
+}
+
+impl<'world_borrow> SystemParamFetch<'world_borrow> for CommandsFetch {
+    type Item = Commands<'world_borrow>;
+
+    fn fetch(
+        &'world_borrow mut self,
+        world: &'world_borrow World,
+        _system_ticks: SystemTicks,
+    ) -> Result<Self::Item, EcsError> {
+        Ok(Commands::new(world))
+    }
+}
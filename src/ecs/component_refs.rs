@@ -0,0 +1,112 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{
+    archetype::Component,
+    change_detection::{ComponentTicks, Tick},
+    column::{ColumnMut, ColumnRef},
+    entities::EntityLocation,
+    world::{EcsError, World},
+};
+
+/// Shared access to a single entity's component.
+/// The component's column stays borrowed until this is dropped.
+pub struct ComponentRef<'world_borrow, T> {
+    column: ColumnRef<'world_borrow, T>,
+    index: usize,
+}
+
+impl<'world_borrow, T> ComponentRef<'world_borrow, T> {
+    pub(crate) fn new(column: ColumnRef<'world_borrow, T>, index: usize) -> Self {
+        Self { column, index }
+    }
+}
+
+impl<T> Deref for ComponentRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.column[self.index]
+    }
+}
+
+/// Exclusive access to a single entity's component.
+/// The component's column stays mutably borrowed until this is dropped.
+/// Mutably dereferencing it marks the component as changed.
+pub struct ComponentMut<'world_borrow, T> {
+    column: ColumnMut<'world_borrow, T>,
+    ticks: &'world_borrow ComponentTicks,
+    change_tick: Tick,
+    index: usize,
+}
+
+impl<'world_borrow, T> ComponentMut<'world_borrow, T> {
+    pub(crate) fn new(
+        column: ColumnMut<'world_borrow, T>,
+        ticks: &'world_borrow ComponentTicks,
+        change_tick: Tick,
+        index: usize,
+    ) -> Self {
+        Self {
+            column,
+            ticks,
+            change_tick,
+            index,
+        }
+    }
+}
+
+impl<T> Deref for ComponentMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.column[self.index]
+    }
+}
+
+impl<T> DerefMut for ComponentMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.ticks.set_changed(self.change_tick);
+        &mut self.column[self.index]
+    }
+}
+
+/// A tuple of component types that can be borrowed together from a single entity.
+pub trait ComponentRefs<'world_borrow> {
+    type Refs;
+
+    fn get_refs(
+        world: &'world_borrow World,
+        location: EntityLocation,
+    ) -> Result<Self::Refs, EcsError>;
+}
+
+macro_rules! component_refs_impl {
+    ($($name:tt),*) => {
+        impl<'world_borrow, $($name: Component),*> ComponentRefs<'world_borrow> for ($($name,)*) {
+            type Refs = ($(ComponentRef<'world_borrow, $name>,)*);
+
+            fn get_refs(
+                world: &'world_borrow World,
+                location: EntityLocation,
+            ) -> Result<Self::Refs, EcsError> {
+                Ok(($(world.component_ref_at::<$name>(location)?,)*))
+            }
+        }
+    };
+}
+
+component_refs_impl!(A);
+component_refs_impl!(A, B);
+component_refs_impl!(A, B, C);
+component_refs_impl!(A, B, C, D);
+component_refs_impl!(A, B, C, D, E);
+component_refs_impl!(A, B, C, D, E, F);
+component_refs_impl!(A, B, C, D, E, F, G);
+component_refs_impl!(A, B, C, D, E, F, G, H);
+component_refs_impl!(A, B, C, D, E, F, G, H, I);
+component_refs_impl!(A, B, C, D, E, F, G, H, I, J);
+component_refs_impl!(A, B, C, D, E, F, G, H, I, J, K);
+component_refs_impl!(A, B, C, D, E, F, G, H, I, J, K, L);
+component_refs_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M);
+component_refs_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M, N);
+component_refs_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O);
@@ -0,0 +1,241 @@
+use std::{
+    alloc::Layout,
+    any::{type_name, TypeId},
+    collections::HashMap,
+    marker::PhantomData,
+    mem::needs_drop,
+};
+
+use super::{
+    archetype::{Component, ComponentId},
+    world::World,
+};
+
+/// Clones the value behind the first pointer into the uninitialized memory behind the second.
+pub type CloneFn = unsafe fn(*const u8, *mut u8);
+
+type SerializeFn = Box<dyn Fn(*const u8) -> String + Send + Sync>;
+
+/// Where the values of a component are kept.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StorageType {
+    /// In the columns of the entity's archetype, fastest to iterate.
+    #[default]
+    Table,
+    /// In a sparse set outside of archetypes, adding and removing it doesn't move the entity.
+    SparseSet,
+}
+
+/// What type-erased storage needs to know about a component type, along with optional
+/// metadata for code that doesn't know the type, like debug printing or scripting.
+pub struct ComponentInfo {
+    id: ComponentId,
+    name: &'static str,
+    type_id: TypeId,
+    layout: Layout,
+    drop: Option<unsafe fn(*mut u8)>,
+    storage_type: StorageType,
+    clone: Option<CloneFn>,
+    serialize: Option<SerializeFn>,
+}
+
+impl ComponentInfo {
+    fn new<T: Component>(id: ComponentId) -> Self {
+        // Safety: callers pass a pointer to a valid 'T' they won't use again
+        unsafe fn drop_ptr<T>(ptr: *mut u8) {
+            ptr.cast::<T>().drop_in_place()
+        }
+        Self {
+            id,
+            name: type_name::<T>(),
+            type_id: TypeId::of::<T>(),
+            layout: Layout::new::<T>(),
+            drop: needs_drop::<T>().then_some(drop_ptr::<T> as unsafe fn(*mut u8)),
+            storage_type: StorageType::Table,
+            clone: None,
+            serialize: None,
+        }
+    }
+
+    pub fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    /// Full type name of the component, as given by 'std::any::type_name'.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn drop(&self) -> Option<unsafe fn(*mut u8)> {
+        self.drop
+    }
+
+    /// Tags are zero-sized components without drop logic.
+    /// They only mark which archetype an entity belongs to, so nothing is stored for them.
+    pub fn is_tag(&self) -> bool {
+        self.layout.size() == 0 && self.drop.is_none()
+    }
+
+    /// Set through 'ComponentRegistration::sparse_set'.
+    pub fn storage_type(&self) -> StorageType {
+        self.storage_type
+    }
+
+    /// Set through 'ComponentRegistration::cloneable'.
+    pub fn clone_fn(&self) -> Option<CloneFn> {
+        self.clone
+    }
+
+    pub fn is_serializable(&self) -> bool {
+        self.serialize.is_some()
+    }
+
+    /// Serialize a value with the function set through 'ComponentRegistration::serialize_with'.
+    /// Returns None if there is none.
+    /// # Safety
+    /// 'component' must point to a valid value of this component's type.
+    pub unsafe fn serialize(&self, component: *const u8) -> Option<String> {
+        self.serialize
+            .as_ref()
+            .map(|serialize| serialize(component))
+    }
+}
+
+/// Typed access to a registered component, to attach metadata to it.
+/// # Example
+/// ```
+/// # use ecs::*;
+/// #[derive(Clone, Debug)]
+/// struct Health(u32);
+/// struct Stunned;
+/// let mut world = World::new();
+/// world
+///     .register_component::<Health>()
+///     .cloneable()
+///     .serialize_with(|health| format!("{:?}", health));
+/// world.register_component::<Stunned>().sparse_set();
+/// ```
+pub struct ComponentRegistration<'a, T: Component> {
+    world: &'a mut World,
+    id: ComponentId,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: Component> ComponentRegistration<'a, T> {
+    pub(crate) fn new(world: &'a mut World, id: ComponentId) -> Self {
+        debug_assert_eq!(world.components().info(id).type_id, TypeId::of::<T>());
+        Self {
+            world,
+            id,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    fn info(&mut self) -> &mut ComponentInfo {
+        self.world.components_mut().info_mut(self.id)
+    }
+
+    /// Store the component in a sparse set instead of archetype columns.
+    /// Has to be called before any entity gets the component.
+    pub fn sparse_set(&mut self) -> &mut Self {
+        let id = self.id;
+        assert!(
+            !self
+                .world
+                .archetypes()
+                .iter()
+                .any(|archetype| archetype.has_component_id(id)),
+            "Storage of {} can't change once it's stored in archetypes",
+            type_name::<T>()
+        );
+        self.info().storage_type = StorageType::SparseSet;
+        self.world.add_sparse_set(id);
+        self
+    }
+
+    /// Let the component be cloned through its 'ComponentInfo'.
+    pub fn cloneable(&mut self) -> &mut Self
+    where
+        T: Clone,
+    {
+        // Safety: callers pass a valid 'T' and memory fit for one
+        unsafe fn clone_ptr<T: Clone>(src: *const u8, dst: *mut u8) {
+            dst.cast::<T>().write((*src.cast::<T>()).clone())
+        }
+        self.info().clone = Some(clone_ptr::<T>);
+        self
+    }
+
+    /// Let the component be turned into a string through its 'ComponentInfo'.
+    pub fn serialize_with(
+        &mut self,
+        serialize: impl Fn(&T) -> String + Send + Sync + 'static,
+    ) -> &mut Self {
+        // Safety: 'ComponentInfo::serialize' requires a pointer to a valid 'T'
+        self.info().serialize = Some(Box::new(move |component| {
+            serialize(unsafe { &*component.cast::<T>() })
+        }));
+        self
+    }
+}
+
+/// Gives every component type a dense 'ComponentId', in registration order.
+/// Types are registered the first time they're stored, or through 'World::register_component'.
+#[derive(Default)]
+pub struct Components {
+    infos: Vec<ComponentInfo>,
+    ids: HashMap<TypeId, ComponentId>,
+}
+
+impl Components {
+    /// Get the id of 'T', registering it first if needed.
+    pub fn register<T: Component>(&mut self) -> ComponentId {
+        let infos = &mut self.infos;
+        *self.ids.entry(TypeId::of::<T>()).or_insert_with(|| {
+            let id = infos.len();
+            infos.push(ComponentInfo::new::<T>(id));
+            id
+        })
+    }
+
+    /// Get the id of 'T', None if it was never registered.
+    pub fn id<T: Component>(&self) -> Option<ComponentId> {
+        self.get_id(TypeId::of::<T>())
+    }
+
+    pub fn get_id(&self, type_id: TypeId) -> Option<ComponentId> {
+        self.ids.get(&type_id).copied()
+    }
+
+    pub fn info(&self, id: ComponentId) -> &ComponentInfo {
+        &self.infos[id]
+    }
+
+    pub(crate) fn info_mut(&mut self, id: ComponentId) -> &mut ComponentInfo {
+        &mut self.infos[id]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ComponentInfo> {
+        self.infos.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.infos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.infos.is_empty()
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
 use thiserror::Error;
 
 use super::archetype::ArchetypeId;
@@ -71,6 +73,10 @@ impl EntityEntry {
 pub(crate) struct Entities {
     entries: Vec<EntityEntry>,
     free: Vec<u32>,
+    /// Entities handed out through 'reserve' that don't have an entry yet.
+    reserved: AtomicU32,
+    /// Entries created by 'flush', until 'free_unclaimed' checks whether they were claimed.
+    unclaimed: Vec<EntityId>,
 }
 
 impl Entities {
@@ -79,6 +85,7 @@ impl Entities {
     }
 
     pub(crate) fn allocate(&mut self) -> Result<Entity, EntityError> {
+        self.flush();
         if let Some(index) = self.free.pop() {
             let entry = &mut self.entries[index as usize];
             if entry.is_live {
@@ -93,6 +100,56 @@ impl Entities {
         }
     }
 
+    /// Hands out a new entity without mutable access.
+    /// Reserved entities aren't live until they're claimed.
+    pub(crate) fn reserve(&self) -> Entity {
+        let offset = self.reserved.fetch_add(1, Ordering::Relaxed);
+        Entity::new(self.len() + offset, 0)
+    }
+
+    /// Creates entries for every reserved entity, they stay dead until claimed.
+    pub(crate) fn flush(&mut self) {
+        let reserved = std::mem::take(self.reserved.get_mut());
+        for _ in 0..reserved {
+            self.unclaimed.push(self.len());
+            self.entries.push(EntityEntry::new(false, 0, 0, 0));
+        }
+    }
+
+    /// Frees the entries of reserved entities that were never claimed, so their indices get
+    /// reused. Their generation is bumped so the reserved handles stay dead.
+    pub(crate) fn free_unclaimed(&mut self) {
+        self.flush();
+        for index in std::mem::take(&mut self.unclaimed) {
+            let entry = &mut self.entries[index as usize];
+            // Claimed entities that were despawned since are already free, with a bumped
+            // generation
+            if !entry.is_live && entry.generation == 0 {
+                entry.generation = 1;
+                self.free.push(index);
+            }
+        }
+    }
+
+    /// Makes a reserved entity live.
+    pub(crate) fn claim(&mut self, entity: Entity) -> Result<(), EntityError> {
+        self.flush();
+        if entity.index >= self.len() {
+            return Err(EntityError::DoesNotExist);
+        }
+
+        let entry = &mut self.entries[entity.index as usize];
+        if entry.is_live {
+            return Err(EntityError::AlreadyAllocated);
+        }
+        if entry.generation != entity.generation {
+            return Err(EntityError::DoesNotExist);
+        }
+
+        entry.is_live = true;
+        Ok(())
+    }
+
     pub(crate) fn deallocate(&mut self, entity: Entity) -> Result<(), EntityError> {
         if entity.index >= self.len() {
             return Err(EntityError::DoesNotExist);
@@ -134,22 +191,20 @@ impl Entities {
         self.entries.iter().filter(|gi| gi.is_live).count()
     }
 
-    pub(crate) fn is_live(&self, entity: Entity) -> bool {
-        if entity.index >= self.len() {
-            return false;
-        }
-        self.entries[entity.index as usize].is_live
-            && self.entries[entity.index as usize].generation == entity.generation
+    /// Builds the handle of the live entity stored at 'index'.
+    pub(crate) fn entity_at_index(&self, index: EntityId) -> Entity {
+        Entity::new(index, self.entries[index as usize].generation)
     }
 
-    pub(crate) fn live_at_index(&self, index: u32) -> Option<&EntityEntry> {
-        self.entries.get(index as usize).and_then(|entry| {
-            if entry.is_live {
-                return Some(entry);
-            }
-            None
-        })
+    /// Location of a live entity, failing if the handle is stale.
+    pub(crate) fn location(&self, entity: Entity) -> Result<EntityLocation, EntityError> {
+        if entity.index >= self.len() {
+            return Err(EntityError::DoesNotExist);
+        }
+        let entry = &self.entries[entity.index as usize];
+        if !entry.is_live || entry.generation != entity.generation {
+            return Err(EntityError::AlreadyDeallocated);
+        }
+        Ok(entry.location)
     }
-
-    pub(crate) fn has_entity(&self, entity: Entity) {}
 }
@@ -0,0 +1,118 @@
+use super::{
+    archetype::{Archetype, Component},
+    component_refs::{ComponentMut, ComponentRef},
+    entities::{Entity, EntityLocation},
+    world::World,
+};
+
+/// Shared access to a single entity, from 'World::entity'.
+/// The entity's location is looked up once, the world can't change while this is alive.
+#[derive(Clone, Copy)]
+pub struct EntityRef<'w> {
+    world: &'w World,
+    entity: Entity,
+    location: EntityLocation,
+}
+
+impl<'w> EntityRef<'w> {
+    pub(crate) fn new(world: &'w World, entity: Entity, location: EntityLocation) -> Self {
+        Self {
+            world,
+            entity,
+            location,
+        }
+    }
+
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn location(&self) -> EntityLocation {
+        self.location
+    }
+
+    pub fn archetype(&self) -> &'w Archetype {
+        self.world.get_archetype(self.location.archetype_id)
+    }
+
+    pub fn contains<T: Component>(&self) -> bool {
+        self.world.has_component_at::<T>(self.location)
+    }
+
+    /// Returns None if the entity lacks the component or it's mutably borrowed elsewhere.
+    pub fn get<T: Component>(&self) -> Option<ComponentRef<'w, T>> {
+        self.world.component_ref_at(self.location).ok()
+    }
+}
+
+/// Exclusive access to a single entity, from 'World::entity_mut'.
+/// The entity's location is looked up once and kept up to date as components are inserted
+/// and removed through this handle.
+pub struct EntityMut<'w> {
+    world: &'w mut World,
+    entity: Entity,
+    location: EntityLocation,
+}
+
+impl<'w> EntityMut<'w> {
+    pub(crate) fn new(world: &'w mut World, entity: Entity, location: EntityLocation) -> Self {
+        Self {
+            world,
+            entity,
+            location,
+        }
+    }
+
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn location(&self) -> EntityLocation {
+        self.location
+    }
+
+    pub fn archetype(&self) -> &Archetype {
+        self.world.get_archetype(self.location.archetype_id)
+    }
+
+    pub fn contains<T: Component>(&self) -> bool {
+        self.world.has_component_at::<T>(self.location)
+    }
+
+    /// Returns None if the entity lacks the component or it's mutably borrowed elsewhere.
+    pub fn get<T: Component>(&self) -> Option<ComponentRef<'_, T>> {
+        self.world.component_ref_at(self.location).ok()
+    }
+
+    /// Returns None if the entity lacks the component or it's borrowed elsewhere.
+    pub fn get_mut<T: Component>(&mut self) -> Option<ComponentMut<'_, T>> {
+        self.world.component_mut_at(self.location)
+    }
+
+    /// Add or overwrite a component, see 'World::add_component'.
+    pub fn insert<T: Component>(&mut self, component: T) -> &mut Self {
+        self.location = self
+            .world
+            .add_component_at(self.entity, self.location, component)
+            .expect("Entity handles always point to a live entity");
+        self
+    }
+
+    /// Remove a component, returning it, see 'World::remove_component'.
+    /// Returns None if the entity lacks it.
+    pub fn remove<T: Component>(&mut self) -> Option<T> {
+        let (component, location) = self
+            .world
+            .remove_component_at(self.entity, self.location)
+            .ok()?;
+        self.location = location;
+        Some(component)
+    }
+
+    /// Despawn the entity, consuming the handle.
+    pub fn despawn(self) {
+        self.world
+            .remove(self.entity)
+            .expect("Entity handles always point to a live entity");
+    }
+}
@@ -0,0 +1,158 @@
+use std::marker::PhantomData;
+
+use super::{
+    change_detection::SystemTicks,
+    resources::{Res, ResMut},
+    systems::{Access, SystemParam, SystemParamFetch},
+    world::{EcsError, World},
+};
+
+pub trait Event: Send + Sync + 'static {}
+
+impl<T: Send + Sync + 'static> Event for T {}
+
+struct EventInstance<E> {
+    id: usize,
+    event: E,
+}
+
+/// Double-buffered event channel, stored as a resource.
+/// Events sent during an update survive the next one, so every system gets to see them
+/// regardless of the order systems run in.
+pub struct Events<E> {
+    previous: Vec<EventInstance<E>>,
+    current: Vec<EventInstance<E>>,
+    event_count: usize,
+}
+
+impl<E> Default for Events<E> {
+    fn default() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            event_count: 0,
+        }
+    }
+}
+
+impl<E: Event> Events<E> {
+    pub fn send(&mut self, event: E) {
+        self.current.push(EventInstance {
+            id: self.event_count,
+            event,
+        });
+        self.event_count += 1;
+    }
+
+    /// Swaps the buffers, dropping events sent two updates ago.
+    /// 'World::update' does this for every event type registered with 'World::add_event'.
+    pub fn update(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+}
+
+/// Remembers which events a reader has already seen.
+pub struct EventCursor<E> {
+    next_id: usize,
+    _data: PhantomData<fn() -> E>,
+}
+
+impl<E> Default for EventCursor<E> {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            _data: PhantomData,
+        }
+    }
+}
+
+impl<E: Event> EventCursor<E> {
+    /// Iterates over the events sent since the last read.
+    pub fn read<'a>(&mut self, events: &'a Events<E>) -> impl Iterator<Item = &'a E> {
+        let next_id = self.next_id;
+        self.next_id = events.event_count;
+        events
+            .previous
+            .iter()
+            .chain(events.current.iter())
+            .filter(move |instance| instance.id >= next_id)
+            .map(|instance| &instance.event)
+    }
+}
+
+/// Sends events of type 'E' from a system.
+pub struct EventWriter<'world_borrow, E: Event> {
+    events: ResMut<'world_borrow, Events<E>>,
+}
+
+impl<E: Event> EventWriter<'_, E> {
+    pub fn send(&mut self, event: E) {
+        self.events.send(event);
+    }
+}
+
+/// Reads events of type 'E' from a system, every system keeps its own cursor.
+pub struct EventReader<'world_borrow, E: Event> {
+    events: Res<'world_borrow, Events<E>>,
+    cursor: &'world_borrow mut EventCursor<E>,
+}
+
+impl<E: Event> EventReader<'_, E> {
+    /// Iterates over the events this system hasn't read yet.
+    pub fn read(&mut self) -> impl Iterator<Item = &E> {
+        self.cursor.read(&self.events)
+    }
+}
+
+pub struct EventWriterFetch<E> {
+    _data: PhantomData<fn() -> E>,
+}
+
+impl<E> Default for EventWriterFetch<E> {
+    fn default() -> Self {
+        Self { _data: PhantomData }
+    }
+}
+
+impl<E: Event> SystemParam for EventWriter<'_, E> {
+    type Fetch = EventWriterFetch<E>;
+    fn access(access: &mut Access) {
+        access.write_resource::<Events<E>>();
+    }
+}
+
+impl<'world_borrow, E: Event> SystemParamFetch<'world_borrow> for EventWriterFetch<E> {
+    type Item = EventWriter<'world_borrow, E>;
+
+    fn fetch(
+        &'world_borrow mut self,
+        world: &'world_borrow World,
+        _system_ticks: SystemTicks,
+    ) -> Result<Self::Item, EcsError> {
+        Ok(EventWriter {
+            events: world.resource_mut::<Events<E>>()?,
+        })
+    }
+}
+
+impl<E: Event> SystemParam for EventReader<'_, E> {
+    type Fetch = EventCursor<E>;
+    fn access(access: &mut Access) {
+        access.read_resource::<Events<E>>();
+    }
+}
+
+impl<'world_borrow, E: Event> SystemParamFetch<'world_borrow> for EventCursor<E> {
+    type Item = EventReader<'world_borrow, E>;
+
+    fn fetch(
+        &'world_borrow mut self,
+        world: &'world_borrow World,
+        _system_ticks: SystemTicks,
+    ) -> Result<Self::Item, EcsError> {
+        Ok(EventReader {
+            events: world.resource::<Events<E>>()?,
+            cursor: self,
+        })
+    }
+}
@@ -0,0 +1,49 @@
+use super::{entities::Entity, world::World};
+
+/// Called with the entity whose component triggered it.
+/// The world is only borrowed, structural changes have to go through 'World::commands'.
+pub type ComponentHook = Box<dyn Fn(&World, Entity) + Send + Sync>;
+
+/// Callbacks run when a component of a given type is added, inserted or removed.
+/// # Example
+/// ```
+/// # use ecs::*;
+/// struct Name(&'static str);
+/// let mut world = World::new();
+/// world
+///     .component_hooks::<Name>()
+///     .on_add(|world, entity| println!("{:?} was named", entity))
+///     .on_remove(|world, entity| println!("{:?} lost its name", entity));
+/// ```
+#[derive(Default)]
+pub struct ComponentHooks {
+    pub(crate) on_add: Option<ComponentHook>,
+    pub(crate) on_insert: Option<ComponentHook>,
+    pub(crate) on_remove: Option<ComponentHook>,
+}
+
+impl ComponentHooks {
+    /// Runs when an entity gets the component while it didn't have it yet.
+    pub fn on_add(&mut self, hook: impl Fn(&World, Entity) + Send + Sync + 'static) -> &mut Self {
+        self.on_add = Some(Box::new(hook));
+        self
+    }
+
+    /// Runs every time the component is written by 'spawn' or 'add_component', after 'on_add'.
+    pub fn on_insert(
+        &mut self,
+        hook: impl Fn(&World, Entity) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_insert = Some(Box::new(hook));
+        self
+    }
+
+    /// Runs before the component is removed or its entity despawned, while it can still be read.
+    pub fn on_remove(
+        &mut self,
+        hook: impl Fn(&World, Entity) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_remove = Some(Box::new(hook));
+        self
+    }
+}
@@ -1,6 +1,23 @@
+// The game only uses part of the ECS so far, the rest is public API waiting for systems that
+// need it.
+#![allow(dead_code)]
+
 mod archetype;
+mod blob_vec;
 mod bundles;
+mod change_detection;
+mod column;
+mod commands;
+mod component_refs;
+mod components;
 mod entities;
+mod entity_refs;
+mod events;
 mod helpers;
+mod hooks;
 mod queries;
+mod removal_detection;
+mod resources;
+mod sparse_set;
+mod systems;
 pub mod world;
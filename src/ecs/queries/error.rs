@@ -1,4 +1,9 @@
 use thiserror::Error;
 
 #[derive(Error, Debug)]
-pub enum FetchError {}
+pub enum FetchError {
+    #[error("Component is already borrowed in a way that conflicts with this query")]
+    AlreadyBorrowed,
+    #[error("Tags aren't change-tracked, so {0} can't be used in Added or Changed")]
+    UntrackedTag(&'static str),
+}
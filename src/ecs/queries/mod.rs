@@ -1,7 +1,9 @@
 mod error;
 mod query;
+pub(crate) mod query_filters;
 mod query_parameters;
 
 pub use error::FetchError;
 pub use query::*;
-pub use query_parameters::{QueryParameterFetch, QueryParameters};
+pub use query_filters::QueryFilter;
+pub use query_parameters::QueryParameters;
@@ -1,26 +1,129 @@
 use std::marker::PhantomData;
 
-use crate::ecs::world::World;
+use crate::ecs::{change_detection::SystemTicks, column::ColumnLock, world::World};
 
 use super::{
     error::FetchError,
-    query_parameters::{QueryParameterFetch, QueryParameters},
+    query_filters::{QueryFilter, QueryFilterFetch},
+    query_parameters::{QueryParameterFetch, QueryParameterIter, QueryParameters},
 };
 
-pub struct QueryFetch<T: QueryParameters> {
-    _data: PhantomData<T>,
+pub struct QueryFetch<T: QueryParameters, F: QueryFilter = ()> {
+    _data: PhantomData<fn() -> (T, F)>,
 }
 
-pub struct Query<'world_borrow, T: QueryParameters> {
-    data: <T as QueryParameterFetch<'world_borrow>>::FetchItem,
-    world: &'world_borrow World,
+impl<T: QueryParameters, F: QueryFilter> Default for QueryFetch<T, F> {
+    fn default() -> Self {
+        Self { _data: PhantomData }
+    }
+}
+
+/// Holds the borrowed data of every archetype matching 'T' and passing the filter 'F'.
+/// Borrows are released when the query is dropped.
+pub struct Query<'world_borrow, T: QueryParameters, F: QueryFilter = ()> {
+    data: Vec<(
+        <T as QueryParameterFetch<'world_borrow>>::FetchItem,
+        <F as QueryFilterFetch<'world_borrow>>::FilterItem,
+    )>,
+    /// Sparse columns are shared by every archetype, so they're borrowed once for the query.
+    /// Declared after 'data' so it's dropped last.
+    _sparse_locks: Vec<ColumnLock<'world_borrow>>,
+}
+
+impl<'world_borrow, T: QueryParameters, F: QueryFilter> Query<'world_borrow, T, F> {
+    /// Iterates over every entity in every matching archetype.
+    pub fn iter(&mut self) -> QueryIter<'_, 'world_borrow, T, F> {
+        QueryIter {
+            archetypes: self.data.iter_mut(),
+            current: None,
+            row: 0,
+        }
+    }
+}
+
+impl<'iter, 'world_borrow, T: QueryParameters, F: QueryFilter> IntoIterator
+    for &'iter mut Query<'world_borrow, T, F>
+{
+    type Item = <QueryIter<'iter, 'world_borrow, T, F> as Iterator>::Item;
+    type IntoIter = QueryIter<'iter, 'world_borrow, T, F>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct QueryIter<'iter, 'world_borrow, T: QueryParameters, F: QueryFilter> {
+    archetypes: std::slice::IterMut<
+        'iter,
+        (
+            <T as QueryParameterFetch<'world_borrow>>::FetchItem,
+            <F as QueryFilterFetch<'world_borrow>>::FilterItem,
+        ),
+    >,
+    current:
+        Option<
+            (
+                <<T as QueryParameterFetch<'world_borrow>>::FetchItem as QueryParameterIter<
+                    'iter,
+                >>::Iter,
+                &'iter <F as QueryFilterFetch<'world_borrow>>::FilterItem,
+            ),
+        >,
+    row: usize,
+}
+
+impl<'iter, 'world_borrow, T: QueryParameters, F: QueryFilter> Iterator
+    for QueryIter<'iter, 'world_borrow, T, F>
+{
+    type Item =
+        <<T as QueryParameterFetch<'world_borrow>>::FetchItem as QueryParameterIter<'iter>>::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((iter, filter)) = self.current.as_mut() {
+                if let Some(item) = iter.next() {
+                    let row = self.row;
+                    self.row += 1;
+                    // Entities missing a sparse component are skipped too
+                    if let Some(item) = item.filter(|_| F::matches_row(filter, row)) {
+                        return Some(item);
+                    }
+                    continue;
+                }
+            }
+            // Current archetype is exhausted, move on to the next one
+            let (fetch, filter) = self.archetypes.next()?;
+            self.current = Some((fetch.iter(), &*filter));
+            self.row = 0;
+        }
+    }
 }
 
-pub fn query<'world_borrow, T: QueryParameters>(
+pub fn query<'world_borrow, T: QueryParameters, F: QueryFilter>(
     world: &'world_borrow World,
-) -> Result<Option<Query<'world_borrow, T>>, FetchError> {
-    Ok(Some(Query {
-        data: T::fetch(world, 0)?,
-        world,
-    }))
+    system_ticks: SystemTicks,
+) -> Result<Query<'world_borrow, T, F>, FetchError> {
+    F::validate(world.components())?;
+    let mut sparse_locks = Vec::new();
+    T::lock_sparse(world, &mut sparse_locks)?;
+    let data = world
+        .archetypes()
+        .iter()
+        .enumerate()
+        .filter(|(_, archetype)| {
+            !archetype.entities.is_empty()
+                && T::matches_archetype(archetype, world.components())
+                && F::matches_archetype(archetype, world.components())
+        })
+        .map(|(archetype_id, _)| {
+            Ok((
+                T::fetch(world, archetype_id, system_ticks)?,
+                F::fetch_filter(world, archetype_id, system_ticks),
+            ))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Query {
+        data,
+        _sparse_locks: sparse_locks,
+    })
 }
@@ -0,0 +1,272 @@
+use std::{any::type_name, marker::PhantomData};
+
+use crate::ecs::{
+    archetype::{Archetype, ArchetypeId, Component},
+    change_detection::{ComponentTicks, SystemTicks},
+    components::{Components, StorageType},
+    systems::Access,
+    world::World,
+};
+
+use super::{error::FetchError, query_parameters::Rows};
+
+/// Restricts which entities a query matches without fetching any data.
+/// Filters are evaluated once per archetype, alongside 'QueryParameters::matches_archetype'.
+/// Filters that depend on component ticks are then evaluated for every row through
+/// 'QueryFilterFetch'.
+pub trait QueryFilter: for<'a> QueryFilterFetch<'a> {
+    fn matches_archetype(archetype: &Archetype, components: &Components) -> bool;
+
+    /// Checked once when the query is built, before any archetype is fetched.
+    fn validate(_components: &Components) -> Result<(), FetchError> {
+        Ok(())
+    }
+
+    /// Filters that compare ticks read them, so they conflict with systems writing 'T'.
+    /// Filters that only look at archetypes don't access anything.
+    fn access(_access: &mut Access) {}
+}
+
+/// Per-archetype state of a filter, fetched alongside the query data.
+/// Like 'QueryParameterFetch', the lifetime lives on the trait.
+pub trait QueryFilterFetch<'world_borrow> {
+    type FilterItem;
+
+    fn fetch_filter(
+        world: &'world_borrow World,
+        archetype_id: ArchetypeId,
+        system_ticks: SystemTicks,
+    ) -> Self::FilterItem;
+    fn matches_row(item: &Self::FilterItem, index: usize) -> bool;
+}
+
+/// Matches archetypes that contain 'T'.
+pub struct With<T>(PhantomData<T>);
+
+/// Matches archetypes that don't contain 'T'.
+pub struct Without<T>(PhantomData<T>);
+
+/// Matches archetypes that pass any of the filters in the tuple 'T'.
+pub struct Or<T>(PhantomData<T>);
+
+/// Matches entities whose 'T' was added since the system last ran.
+/// Tags aren't tracked, so building a query with 'Added' on one fails with
+/// 'FetchError::UntrackedTag'; 'ComponentHooks::on_add' can be used instead.
+pub struct Added<T>(PhantomData<T>);
+
+/// Matches entities whose 'T' was added or mutably accessed since the system last ran.
+/// Tags aren't tracked, so building a query with 'Changed' on one fails with
+/// 'FetchError::UntrackedTag'.
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for With<T> {
+    fn matches_archetype(archetype: &Archetype, components: &Components) -> bool {
+        archetype.may_have_component::<T>(components)
+    }
+}
+
+impl<T: Component> QueryFilter for Without<T> {
+    fn matches_archetype(archetype: &Archetype, components: &Components) -> bool {
+        !archetype.has_component::<T>(components)
+    }
+}
+
+impl<T: Component> QueryFilter for Added<T> {
+    fn matches_archetype(archetype: &Archetype, components: &Components) -> bool {
+        archetype.may_have_component::<T>(components)
+    }
+
+    fn validate(components: &Components) -> Result<(), FetchError> {
+        validate_tracked::<T>(components)
+    }
+
+    fn access(access: &mut Access) {
+        access.read_component::<T>();
+    }
+}
+
+impl<T: Component> QueryFilter for Changed<T> {
+    fn matches_archetype(archetype: &Archetype, components: &Components) -> bool {
+        archetype.may_have_component::<T>(components)
+    }
+
+    fn validate(components: &Components) -> Result<(), FetchError> {
+        validate_tracked::<T>(components)
+    }
+
+    fn access(access: &mut Access) {
+        access.read_component::<T>();
+    }
+}
+
+/// Tags have no ticks of their own, so tick filters would silently never match them.
+/// Zero-sized components in sparse sets are stored, and tracked, like any other.
+fn validate_tracked<T: Component>(components: &Components) -> Result<(), FetchError> {
+    let untracked = components.id::<T>().is_some_and(|component_id| {
+        let info = components.info(component_id);
+        info.storage_type() == StorageType::Table && info.is_tag()
+    });
+    if untracked {
+        return Err(FetchError::UntrackedTag(type_name::<T>()));
+    }
+    Ok(())
+}
+
+impl QueryFilter for () {
+    fn matches_archetype(_archetype: &Archetype, _components: &Components) -> bool {
+        true
+    }
+}
+
+// Filters may be fetched for archetypes they don't match when nested in 'Or',
+// so they remember whether the archetype has 'T' at all.
+// Sparse components have to be looked up for every entity instead.
+impl<'world_borrow, T: Component> QueryFilterFetch<'world_borrow> for With<T> {
+    type FilterItem = Option<Rows<'world_borrow>>;
+
+    fn fetch_filter(
+        world: &'world_borrow World,
+        archetype_id: ArchetypeId,
+        _system_ticks: SystemTicks,
+    ) -> Self::FilterItem {
+        Rows::new::<T>(world, archetype_id)
+    }
+    fn matches_row(item: &Self::FilterItem, index: usize) -> bool {
+        item.is_some_and(|rows| rows.get(index).is_some())
+    }
+}
+
+impl<'world_borrow, T: Component> QueryFilterFetch<'world_borrow> for Without<T> {
+    type FilterItem = <With<T> as QueryFilterFetch<'world_borrow>>::FilterItem;
+
+    fn fetch_filter(
+        world: &'world_borrow World,
+        archetype_id: ArchetypeId,
+        system_ticks: SystemTicks,
+    ) -> Self::FilterItem {
+        With::<T>::fetch_filter(world, archetype_id, system_ticks)
+    }
+    fn matches_row(item: &Self::FilterItem, index: usize) -> bool {
+        !With::<T>::matches_row(item, index)
+    }
+}
+
+macro_rules! tick_filter_fetch_impl {
+    ($filter:ident, $is_newer:ident) => {
+        impl<'world_borrow, T: Component> QueryFilterFetch<'world_borrow> for $filter<T> {
+            type FilterItem = Option<(
+                &'world_borrow [ComponentTicks],
+                Rows<'world_borrow>,
+                SystemTicks,
+            )>;
+
+            fn fetch_filter(
+                world: &'world_borrow World,
+                archetype_id: ArchetypeId,
+                system_ticks: SystemTicks,
+            ) -> Self::FilterItem {
+                Rows::new::<T>(world, archetype_id).map(|rows| (rows.ticks(), rows, system_ticks))
+            }
+            fn matches_row(item: &Self::FilterItem, index: usize) -> bool {
+                item.map_or(false, |(ticks, rows, system_ticks)| {
+                    rows.get(index)
+                        .is_some_and(|index| ticks[index].$is_newer(system_ticks))
+                })
+            }
+        }
+    };
+}
+
+tick_filter_fetch_impl!(Added, is_added);
+tick_filter_fetch_impl!(Changed, is_changed);
+
+impl<'world_borrow> QueryFilterFetch<'world_borrow> for () {
+    type FilterItem = ();
+
+    fn fetch_filter(
+        _world: &'world_borrow World,
+        _archetype_id: ArchetypeId,
+        _system_ticks: SystemTicks,
+    ) -> Self::FilterItem {
+    }
+    fn matches_row(_item: &Self::FilterItem, _index: usize) -> bool {
+        true
+    }
+}
+
+macro_rules! query_filter_impl {
+    ($($name:tt $index:tt),*) => {
+        impl<$($name: QueryFilter),*> QueryFilter for ($($name,)*) {
+            fn matches_archetype(archetype: &Archetype, components: &Components) -> bool {
+                $($name::matches_archetype(archetype, components))&&*
+            }
+
+            fn validate(components: &Components) -> Result<(), FetchError> {
+                $($name::validate(components)?;)*
+                Ok(())
+            }
+
+            fn access(access: &mut Access) {
+                $($name::access(access);)*
+            }
+        }
+
+        impl<'world_borrow, $($name: QueryFilter),*> QueryFilterFetch<'world_borrow>
+            for ($($name,)*)
+        {
+            type FilterItem = ($(<$name as QueryFilterFetch<'world_borrow>>::FilterItem,)*);
+
+            fn fetch_filter(
+                world: &'world_borrow World,
+                archetype_id: ArchetypeId,
+                system_ticks: SystemTicks,
+            ) -> Self::FilterItem {
+                ($($name::fetch_filter(world, archetype_id, system_ticks),)*)
+            }
+            fn matches_row(item: &Self::FilterItem, index: usize) -> bool {
+                $($name::matches_row(&item.$index, index))&&*
+            }
+        }
+
+        impl<$($name: QueryFilter),*> QueryFilter for Or<($($name,)*)> {
+            fn matches_archetype(archetype: &Archetype, components: &Components) -> bool {
+                $($name::matches_archetype(archetype, components))||*
+            }
+
+            fn validate(components: &Components) -> Result<(), FetchError> {
+                $($name::validate(components)?;)*
+                Ok(())
+            }
+
+            fn access(access: &mut Access) {
+                $($name::access(access);)*
+            }
+        }
+
+        impl<'world_borrow, $($name: QueryFilter),*> QueryFilterFetch<'world_borrow>
+            for Or<($($name,)*)>
+        {
+            type FilterItem = ($(<$name as QueryFilterFetch<'world_borrow>>::FilterItem,)*);
+
+            fn fetch_filter(
+                world: &'world_borrow World,
+                archetype_id: ArchetypeId,
+                system_ticks: SystemTicks,
+            ) -> Self::FilterItem {
+                ($($name::fetch_filter(world, archetype_id, system_ticks),)*)
+            }
+            fn matches_row(item: &Self::FilterItem, index: usize) -> bool {
+                $($name::matches_row(&item.$index, index))||*
+            }
+        }
+    };
+}
+
+query_filter_impl!(A 0);
+query_filter_impl!(A 0, B 1);
+query_filter_impl!(A 0, B 1, C 2);
+query_filter_impl!(A 0, B 1, C 2, D 3);
+query_filter_impl!(A 0, B 1, C 2, D 3, E 4);
+query_filter_impl!(A 0, B 1, C 2, D 3, E 4, F 5);
+query_filter_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+query_filter_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
@@ -1,25 +1,39 @@
-use std::{
-    any::TypeId,
-    marker::PhantomData,
-    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
-};
+use std::marker::PhantomData;
 
 use crate::ecs::{
     archetype::{Archetype, ArchetypeId, Component},
+    change_detection::{ComponentTicks, Mut, Ref, SystemTicks, TAG_TICKS},
+    column::{Column, ColumnLock, ColumnMut, ColumnRef},
+    components::Components,
+    entities::{Entities, Entity, EntityId},
+    sparse_set::ComponentSparseSet,
+    systems::Access,
     world::World,
 };
 
 use super::error::FetchError;
 
 pub trait QueryParameterFetch<'world_borrow> {
-    type FetchItem;
+    type FetchItem: for<'iter> QueryParameterIter<'iter>;
 
     fn fetch(
         world: &'world_borrow World,
         archetype: ArchetypeId,
+        system_ticks: SystemTicks,
     ) -> Result<Self::FetchItem, FetchError>;
 }
 
+/// Iterates over the data fetched from a single archetype, yielding once per entity.
+/// Entities missing the data, like a sparse component they don't have, yield 'None'.
+/// Like 'QueryParameterFetch', the lifetime lives on the trait so the iterator can be
+/// requested for any borrow of the fetched data.
+pub trait QueryParameterIter<'iter> {
+    type Item;
+    type Iter: Iterator<Item = Option<Self::Item>>;
+
+    fn iter(&'iter mut self) -> Self::Iter;
+}
+
 pub struct QueryParameterFetchRead<T> {
     _data: PhantomData<T>,
 }
@@ -28,49 +42,427 @@ pub struct QueryParameterFetchWrite<T> {
     _data: PhantomData<T>,
 }
 
+pub struct QueryParameterFetchRef<T> {
+    _data: PhantomData<T>,
+}
+
+/// Where the values of a component are kept, see 'Rows'.
+#[derive(Clone, Copy)]
+enum Storage<'world_borrow> {
+    Table(&'world_borrow Column),
+    Sparse(&'world_borrow ComponentSparseSet),
+    /// Tags aren't stored, every entity shares the same zero-sized value.
+    Tag,
+}
+
+/// Maps the entities of an archetype to the values of a component.
+/// Archetype columns are in entity order, sparse sets have to be looked up by entity.
+#[derive(Clone, Copy)]
+pub struct Rows<'world_borrow> {
+    entities: &'world_borrow [EntityId],
+    storage: Storage<'world_borrow>,
+}
+
+impl<'world_borrow> Rows<'world_borrow> {
+    /// Rows of 'T' for the entities of an archetype, None if none of them can have one.
+    pub(crate) fn new<T: Component>(
+        world: &'world_borrow World,
+        archetype_id: ArchetypeId,
+    ) -> Option<Self> {
+        let archetype = world.get_archetype(archetype_id);
+        let component_id = world.components().id::<T>()?;
+        let storage = match world.sparse_set(component_id) {
+            Some(sparse_set) => Storage::Sparse(sparse_set),
+            None => match archetype.get_column(component_id) {
+                Some(column) => Storage::Table(column),
+                None if archetype.has_component_id(component_id) => Storage::Tag,
+                None => return None,
+            },
+        };
+        Some(Self {
+            entities: &archetype.entities,
+            storage,
+        })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Index of the value of the entity at 'row', None if it has none.
+    pub(crate) fn get(&self, row: usize) -> Option<usize> {
+        match self.storage {
+            Storage::Table(_) => Some(row),
+            Storage::Sparse(sparse_set) => sparse_set.dense_index(self.entities[row]),
+            Storage::Tag => Some(0),
+        }
+    }
+
+    pub(crate) fn ticks(&self) -> &'world_borrow [ComponentTicks] {
+        match self.storage {
+            Storage::Table(column) => &column.ticks,
+            Storage::Sparse(sparse_set) => &sparse_set.column().ticks,
+            Storage::Tag => std::slice::from_ref(&TAG_TICKS),
+        }
+    }
+
+    fn read<T: Component>(&self) -> Result<ColumnRef<'world_borrow, T>, FetchError> {
+        match self.storage {
+            Storage::Table(column) => column.try_read().ok_or(FetchError::AlreadyBorrowed),
+            // Safety: the query locked the column in 'lock_sparse_column'
+            Storage::Sparse(sparse_set) => Ok(unsafe { sparse_set.column().read_unguarded() }),
+            Storage::Tag => Ok(ColumnRef::tag(1)),
+        }
+    }
+
+    fn write<T: Component>(&self) -> Result<ColumnMut<'world_borrow, T>, FetchError> {
+        match self.storage {
+            Storage::Table(column) => column.try_write().ok_or(FetchError::AlreadyBorrowed),
+            // Safety: the query locked the column in 'lock_sparse_column', and archetypes never
+            // share entities so they access different values
+            Storage::Sparse(sparse_set) => Ok(unsafe { sparse_set.column().write_unguarded() }),
+            Storage::Tag => Ok(ColumnMut::tag(1)),
+        }
+    }
+}
+
+/// Sparse columns are shared by every archetype, so queries lock them once up front and
+/// archetypes fetch them without going through the borrow flag.
+fn lock_sparse_column<'world_borrow, T: Component>(
+    world: &'world_borrow World,
+    write: bool,
+    locks: &mut Vec<ColumnLock<'world_borrow>>,
+) -> Result<(), FetchError> {
+    let sparse_set = world
+        .components()
+        .id::<T>()
+        .and_then(|component_id| world.sparse_set(component_id));
+    if let Some(sparse_set) = sparse_set {
+        locks.push(
+            sparse_set
+                .column()
+                .try_lock(write)
+                .ok_or(FetchError::AlreadyBorrowed)?,
+        );
+    }
+    Ok(())
+}
+
 impl<'world_borrow, T: Component> QueryParameterFetch<'world_borrow>
     for QueryParameterFetchRead<T>
 {
-    type FetchItem = RwLockReadGuard<'world_borrow, Vec<T>>;
+    type FetchItem = ReadFetch<'world_borrow, T>;
 
     fn fetch(
         world: &'world_borrow World,
         archetype_id: ArchetypeId,
+        _system_ticks: SystemTicks,
     ) -> Result<Self::FetchItem, FetchError> {
-        let archetype = world.get_archetype(archetype_id);
-        Ok(archetype
-            .components
-            .get(&TypeId::of::<T>())
-            .unwrap()
-            .data
-            .to_any()
-            .downcast_ref::<RwLock<Vec<T>>>()
-            .unwrap()
-            .try_read()
-            .unwrap())
+        let rows = Rows::new::<T>(world, archetype_id).unwrap();
+        Ok(ReadFetch {
+            data: rows.read()?,
+            rows,
+        })
     }
 }
 
 impl<'world_borrow, T: Component> QueryParameterFetch<'world_borrow>
     for QueryParameterFetchWrite<T>
 {
-    type FetchItem = RwLockWriteGuard<'world_borrow, Vec<T>>;
+    type FetchItem = WriteFetch<'world_borrow, T>;
+
+    fn fetch(
+        world: &'world_borrow World,
+        archetype_id: ArchetypeId,
+        system_ticks: SystemTicks,
+    ) -> Result<Self::FetchItem, FetchError> {
+        let rows = Rows::new::<T>(world, archetype_id).unwrap();
+        Ok(WriteFetch {
+            data: rows.write()?,
+            ticks: rows.ticks(),
+            rows,
+            system_ticks,
+        })
+    }
+}
+
+impl<'world_borrow, T: Component> QueryParameterFetch<'world_borrow> for QueryParameterFetchRef<T> {
+    type FetchItem = RefFetch<'world_borrow, T>;
+
+    fn fetch(
+        world: &'world_borrow World,
+        archetype_id: ArchetypeId,
+        system_ticks: SystemTicks,
+    ) -> Result<Self::FetchItem, FetchError> {
+        let rows = Rows::new::<T>(world, archetype_id).unwrap();
+        Ok(RefFetch {
+            data: rows.read()?,
+            ticks: rows.ticks(),
+            rows,
+            system_ticks,
+        })
+    }
+}
+
+/// A read-locked column, along with the rows mapping entities to its values.
+pub struct ReadFetch<'world_borrow, T> {
+    data: ColumnRef<'world_borrow, T>,
+    rows: Rows<'world_borrow>,
+}
+
+impl<'iter, 'world_borrow, T: Component> QueryParameterIter<'iter> for ReadFetch<'world_borrow, T> {
+    type Item = &'iter T;
+    type Iter = ReadIter<'iter, T>;
+
+    fn iter(&'iter mut self) -> Self::Iter {
+        ReadIter {
+            data: &self.data,
+            rows: self.rows,
+            row: 0,
+        }
+    }
+}
+
+pub struct ReadIter<'iter, T> {
+    data: &'iter [T],
+    rows: Rows<'iter>,
+    row: usize,
+}
+
+impl<'iter, T> Iterator for ReadIter<'iter, T> {
+    type Item = Option<&'iter T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.row == self.rows.len() {
+            return None;
+        }
+        let index = self.rows.get(self.row);
+        self.row += 1;
+        Some(index.map(|index| &self.data[index]))
+    }
+}
+
+/// A write-locked column, along with the ticks its writes are stamped on.
+pub struct WriteFetch<'world_borrow, T> {
+    data: ColumnMut<'world_borrow, T>,
+    ticks: &'world_borrow [ComponentTicks],
+    rows: Rows<'world_borrow>,
+    system_ticks: SystemTicks,
+}
+
+impl<'iter, 'world_borrow, T: Component> QueryParameterIter<'iter>
+    for WriteFetch<'world_borrow, T>
+{
+    type Item = Mut<'iter, T>;
+    type Iter = WriteIter<'iter, T>;
+
+    fn iter(&'iter mut self) -> Self::Iter {
+        WriteIter {
+            data: self.data.as_mut_ptr(),
+            len: ColumnMut::len(&self.data),
+            ticks: self.ticks,
+            rows: self.rows,
+            row: 0,
+            system_ticks: self.system_ticks,
+            _marker: PhantomData,
+        }
+    }
+}
+
+pub struct WriteIter<'iter, T> {
+    data: *mut T,
+    len: usize,
+    ticks: &'iter [ComponentTicks],
+    rows: Rows<'iter>,
+    row: usize,
+    system_ticks: SystemTicks,
+    _marker: PhantomData<&'iter mut [T]>,
+}
+
+impl<'iter, T> Iterator for WriteIter<'iter, T> {
+    type Item = Option<Mut<'iter, T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.row == self.rows.len() {
+            return None;
+        }
+        let index = self.rows.get(self.row);
+        self.row += 1;
+        Some(index.map(|index| {
+            assert!(index < self.len);
+            Mut {
+                // Safety: the column is borrowed mutably for 'iter, and every entity maps to
+                // its own index so no value is handed out twice
+                value: unsafe { &mut *self.data.add(index) },
+                ticks: &self.ticks[index],
+                system_ticks: self.system_ticks,
+            }
+        }))
+    }
+}
+
+/// A read-locked column, along with its ticks so items can tell if they changed.
+pub struct RefFetch<'world_borrow, T> {
+    data: ColumnRef<'world_borrow, T>,
+    ticks: &'world_borrow [ComponentTicks],
+    rows: Rows<'world_borrow>,
+    system_ticks: SystemTicks,
+}
+
+impl<'iter, 'world_borrow, T: Component> QueryParameterIter<'iter> for RefFetch<'world_borrow, T> {
+    type Item = Ref<'iter, T>;
+    type Iter = RefIter<'iter, T>;
+
+    fn iter(&'iter mut self) -> Self::Iter {
+        RefIter {
+            data: &self.data,
+            ticks: self.ticks,
+            rows: self.rows,
+            row: 0,
+            system_ticks: self.system_ticks,
+        }
+    }
+}
+
+pub struct RefIter<'iter, T> {
+    data: &'iter [T],
+    ticks: &'iter [ComponentTicks],
+    rows: Rows<'iter>,
+    row: usize,
+    system_ticks: SystemTicks,
+}
+
+impl<'iter, T> Iterator for RefIter<'iter, T> {
+    type Item = Option<Ref<'iter, T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.row == self.rows.len() {
+            return None;
+        }
+        let index = self.rows.get(self.row);
+        self.row += 1;
+        Some(index.map(|index| Ref {
+            value: &self.data[index],
+            ticks: &self.ticks[index],
+            system_ticks: self.system_ticks,
+        }))
+    }
+}
+
+pub struct QueryParameterFetchOption<T> {
+    _data: PhantomData<T>,
+}
+
+impl<'world_borrow, T: QueryParameter> QueryParameterFetch<'world_borrow>
+    for QueryParameterFetchOption<T>
+{
+    type FetchItem =
+        OptionFetch<<T::QueryParameterFetch as QueryParameterFetch<'world_borrow>>::FetchItem>;
 
     fn fetch(
         world: &'world_borrow World,
         archetype_id: ArchetypeId,
+        system_ticks: SystemTicks,
     ) -> Result<Self::FetchItem, FetchError> {
         let archetype = world.get_archetype(archetype_id);
-        Ok(archetype
-            .components
-            .get(&TypeId::of::<T>())
-            .unwrap()
-            .data
-            .to_any()
-            .downcast_ref::<RwLock<Vec<T>>>()
-            .unwrap()
-            .try_write()
-            .unwrap())
+        let data = if <T as QueryParameter>::matches_archetype(archetype, world.components()) {
+            Some(T::QueryParameterFetch::fetch(
+                world,
+                archetype_id,
+                system_ticks,
+            )?)
+        } else {
+            None
+        };
+        Ok(OptionFetch {
+            data,
+            len: archetype.entities.len(),
+        })
+    }
+}
+
+/// Data fetched for an optional parameter, 'None' if the archetype lacks the component.
+pub struct OptionFetch<F> {
+    data: Option<F>,
+    len: usize,
+}
+
+impl<'iter, F: QueryParameterIter<'iter>> QueryParameterIter<'iter> for OptionFetch<F> {
+    type Item = Option<F::Item>;
+    type Iter = OptionIter<F::Iter>;
+
+    fn iter(&'iter mut self) -> Self::Iter {
+        OptionIter {
+            inner: self.data.as_mut().map(|data| data.iter()),
+            remaining: self.len,
+        }
+    }
+}
+
+/// Yields an item for every entity, 'None' for the ones missing the data.
+pub struct OptionIter<I> {
+    inner: Option<I>,
+    remaining: usize,
+}
+
+impl<T, I: Iterator<Item = Option<T>>> Iterator for OptionIter<I> {
+    type Item = Option<Option<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(Some(self.inner.as_mut().and_then(Iterator::next).flatten()))
+    }
+}
+
+pub struct QueryParameterFetchEntity;
+
+impl<'world_borrow> QueryParameterFetch<'world_borrow> for QueryParameterFetchEntity {
+    type FetchItem = EntityFetch<'world_borrow>;
+
+    fn fetch(
+        world: &'world_borrow World,
+        archetype_id: ArchetypeId,
+        _system_ticks: SystemTicks,
+    ) -> Result<Self::FetchItem, FetchError> {
+        Ok(EntityFetch {
+            entity_ids: &world.get_archetype(archetype_id).entities,
+            entities: world.entities(),
+        })
+    }
+}
+
+/// The entities of an archetype, paired with 'Entities' to recover their generations.
+pub struct EntityFetch<'world_borrow> {
+    entity_ids: &'world_borrow [EntityId],
+    entities: &'world_borrow Entities,
+}
+
+impl<'iter, 'world_borrow> QueryParameterIter<'iter> for EntityFetch<'world_borrow> {
+    type Item = Entity;
+    type Iter = EntityIter<'iter>;
+
+    fn iter(&'iter mut self) -> Self::Iter {
+        EntityIter {
+            entity_ids: self.entity_ids.iter(),
+            entities: self.entities,
+        }
+    }
+}
+
+pub struct EntityIter<'iter> {
+    entity_ids: std::slice::Iter<'iter, EntityId>,
+    entities: &'iter Entities,
+}
+
+impl Iterator for EntityIter<'_> {
+    type Item = Option<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entity_id = *self.entity_ids.next()?;
+        Some(Some(self.entities.entity_at_index(entity_id)))
     }
 }
 
@@ -80,21 +472,223 @@ impl<'world_borrow, T: Component> QueryParameterFetch<'world_borrow>
 /// In the future this can (hopefully) be made better with Generic Associated Types.
 pub trait QueryParameter {
     type QueryParameterFetch: for<'a> QueryParameterFetch<'a>;
-    fn matches_archetype(archetype: &Archetype) -> bool;
+    fn matches_archetype(archetype: &Archetype, components: &Components) -> bool;
+    fn access(access: &mut Access);
+    fn lock_sparse<'world_borrow>(
+        _world: &'world_borrow World,
+        _locks: &mut Vec<ColumnLock<'world_borrow>>,
+    ) -> Result<(), FetchError> {
+        Ok(())
+    }
 }
 
 impl<T: Component> QueryParameter for &T {
     type QueryParameterFetch = QueryParameterFetchRead<T>;
-    fn matches_archetype(archetype: &Archetype) -> bool {
-        archetype.has_component::<T>()
+    fn matches_archetype(archetype: &Archetype, components: &Components) -> bool {
+        archetype.may_have_component::<T>(components)
+    }
+    fn access(access: &mut Access) {
+        access.read_component::<T>();
+    }
+    fn lock_sparse<'world_borrow>(
+        world: &'world_borrow World,
+        locks: &mut Vec<ColumnLock<'world_borrow>>,
+    ) -> Result<(), FetchError> {
+        lock_sparse_column::<T>(world, false, locks)
     }
 }
 
 impl<T: Component> QueryParameter for &mut T {
     type QueryParameterFetch = QueryParameterFetchWrite<T>;
-    fn matches_archetype(archetype: &Archetype) -> bool {
-        archetype.has_component::<T>()
+    fn matches_archetype(archetype: &Archetype, components: &Components) -> bool {
+        archetype.may_have_component::<T>(components)
+    }
+    fn access(access: &mut Access) {
+        access.write_component::<T>();
+    }
+    fn lock_sparse<'world_borrow>(
+        world: &'world_borrow World,
+        locks: &mut Vec<ColumnLock<'world_borrow>>,
+    ) -> Result<(), FetchError> {
+        lock_sparse_column::<T>(world, true, locks)
+    }
+}
+
+/// Like '&T', but items know whether the component was added or changed.
+impl<T: Component> QueryParameter for Ref<'_, T> {
+    type QueryParameterFetch = QueryParameterFetchRef<T>;
+    fn matches_archetype(archetype: &Archetype, components: &Components) -> bool {
+        archetype.may_have_component::<T>(components)
+    }
+    fn access(access: &mut Access) {
+        access.read_component::<T>();
     }
+    fn lock_sparse<'world_borrow>(
+        world: &'world_borrow World,
+        locks: &mut Vec<ColumnLock<'world_borrow>>,
+    ) -> Result<(), FetchError> {
+        lock_sparse_column::<T>(world, false, locks)
+    }
+}
+
+impl QueryParameter for Entity {
+    type QueryParameterFetch = QueryParameterFetchEntity;
+    fn matches_archetype(_archetype: &Archetype, _components: &Components) -> bool {
+        true
+    }
+    fn access(_access: &mut Access) {}
+}
+
+/// Matches every archetype, yielding 'None' when the archetype doesn't match 'T'.
+impl<T: QueryParameter> QueryParameter for Option<T> {
+    type QueryParameterFetch = QueryParameterFetchOption<T>;
+    fn matches_archetype(_archetype: &Archetype, _components: &Components) -> bool {
+        true
+    }
+    fn access(access: &mut Access) {
+        <T as QueryParameter>::access(access);
+    }
+    fn lock_sparse<'world_borrow>(
+        world: &'world_borrow World,
+        locks: &mut Vec<ColumnLock<'world_borrow>>,
+    ) -> Result<(), FetchError> {
+        <T as QueryParameter>::lock_sparse(world, locks)
+    }
+}
+
+/// Everything that can be passed to 'World::query'.
+/// 'matches_archetype' decides which archetypes are fetched, the fetched data is then iterated
+/// archetype by archetype.
+/// 'access' declares which components are read and written, for system scheduling.
+/// 'lock_sparse' borrows the sparse components once for the whole query.
+pub trait QueryParameters: for<'a> QueryParameterFetch<'a> {
+    fn matches_archetype(archetype: &Archetype, components: &Components) -> bool;
+    fn access(access: &mut Access);
+    fn lock_sparse<'world_borrow>(
+        world: &'world_borrow World,
+        locks: &mut Vec<ColumnLock<'world_borrow>>,
+    ) -> Result<(), FetchError>;
+}
+
+impl<'world_borrow, T: QueryParameter> QueryParameterFetch<'world_borrow> for T {
+    type FetchItem = <T::QueryParameterFetch as QueryParameterFetch<'world_borrow>>::FetchItem;
+
+    fn fetch(
+        world: &'world_borrow World,
+        archetype_id: ArchetypeId,
+        system_ticks: SystemTicks,
+    ) -> Result<Self::FetchItem, FetchError> {
+        T::QueryParameterFetch::fetch(world, archetype_id, system_ticks)
+    }
+}
+
+impl<T: QueryParameter> QueryParameters for T {
+    fn matches_archetype(archetype: &Archetype, components: &Components) -> bool {
+        <T as QueryParameter>::matches_archetype(archetype, components)
+    }
+    fn access(access: &mut Access) {
+        <T as QueryParameter>::access(access);
+    }
+    fn lock_sparse<'world_borrow>(
+        world: &'world_borrow World,
+        locks: &mut Vec<ColumnLock<'world_borrow>>,
+    ) -> Result<(), FetchError> {
+        <T as QueryParameter>::lock_sparse(world, locks)
+    }
+}
+
+/// Walks several per-archetype iterators in lockstep.
+/// They all yield once per entity, so they run out together.
+/// An entity missing any of the data is missing the whole tuple.
+pub struct ZipIter<T>(T);
+
+/// Iterators yielding once per entity, so 'ZipIter' can name the type of their items.
+pub trait RowIterator {
+    type RowItem;
+
+    fn next_row(&mut self) -> Option<Option<Self::RowItem>>;
+}
+
+impl<T, I: Iterator<Item = Option<T>>> RowIterator for I {
+    type RowItem = T;
+
+    fn next_row(&mut self) -> Option<Option<T>> {
+        self.next()
+    }
+}
+
+macro_rules! query_parameters_impl {
+    ($($name:tt $index:tt),*) => {
+        impl<'world_borrow, $($name: QueryParameter),*> QueryParameterFetch<'world_borrow>
+            for ($($name,)*)
+        {
+            type FetchItem =
+                ($(<$name::QueryParameterFetch as QueryParameterFetch<'world_borrow>>::FetchItem,)*);
+
+            fn fetch(
+                world: &'world_borrow World,
+                archetype_id: ArchetypeId,
+                system_ticks: SystemTicks,
+            ) -> Result<Self::FetchItem, FetchError> {
+                Ok(($($name::QueryParameterFetch::fetch(world, archetype_id, system_ticks)?,)*))
+            }
+        }
+
+        impl<$($name: QueryParameter),*> QueryParameters for ($($name,)*) {
+            fn matches_archetype(archetype: &Archetype, components: &Components) -> bool {
+                $(<$name as QueryParameter>::matches_archetype(archetype, components))&&*
+            }
+            fn access(access: &mut Access) {
+                $(<$name as QueryParameter>::access(access);)*
+            }
+            fn lock_sparse<'world_borrow>(
+                world: &'world_borrow World,
+                locks: &mut Vec<ColumnLock<'world_borrow>>,
+            ) -> Result<(), FetchError> {
+                $(<$name as QueryParameter>::lock_sparse(world, locks)?;)*
+                Ok(())
+            }
+        }
+
+        impl<'iter, $($name: QueryParameterIter<'iter>),*> QueryParameterIter<'iter>
+            for ($($name,)*)
+        {
+            type Item = ($($name::Item,)*);
+            type Iter = ZipIter<($($name::Iter,)*)>;
+
+            fn iter(&'iter mut self) -> Self::Iter {
+                ZipIter(($(self.$index.iter(),)*))
+            }
+        }
+
+        impl<$($name: RowIterator),*> Iterator for ZipIter<($($name,)*)> {
+            type Item = Option<($($name::RowItem,)*)>;
+
+            #[allow(non_snake_case)]
+            fn next(&mut self) -> Option<Self::Item> {
+                // Every iterator is advanced, even once the entity is known to be missing data
+                let row = ($(self.0.$index.next_row()?,)*);
+                Some(match row {
+                    ($(Some($name),)*) => Some(($($name,)*)),
+                    _ => None,
+                })
+            }
+        }
+    };
 }
 
-pub trait QueryParameters: for<'a> QueryParameterFetch<'a> {}
+query_parameters_impl!(A 0);
+query_parameters_impl!(A 0, B 1);
+query_parameters_impl!(A 0, B 1, C 2);
+query_parameters_impl!(A 0, B 1, C 2, D 3);
+query_parameters_impl!(A 0, B 1, C 2, D 3, E 4);
+query_parameters_impl!(A 0, B 1, C 2, D 3, E 4, F 5);
+query_parameters_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+query_parameters_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+query_parameters_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+query_parameters_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+query_parameters_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+query_parameters_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+query_parameters_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12);
+query_parameters_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12, N 13);
+query_parameters_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12, N 13, O 14);
@@ -0,0 +1,66 @@
+use std::marker::PhantomData;
+
+use super::{
+    archetype::Component,
+    change_detection::SystemTicks,
+    entities::Entity,
+    events::{EventCursor, Events},
+    systems::{Access, SystemParam, SystemParamFetch},
+    world::{EcsError, World},
+};
+
+/// Reads the entities that lost a 'T', through 'World::remove_component' or a despawn.
+/// Removals are kept for two updates like events, every system keeps its own cursor.
+pub struct RemovedComponents<'world_borrow, T: Component> {
+    removed: Option<&'world_borrow Events<Entity>>,
+    cursor: &'world_borrow mut EventCursor<Entity>,
+    _data: PhantomData<fn() -> T>,
+}
+
+impl<T: Component> RemovedComponents<'_, T> {
+    /// Iterates over the entities that lost 'T' since this system last read them.
+    pub fn read(&mut self) -> impl Iterator<Item = Entity> + '_ {
+        let cursor = &mut *self.cursor;
+        self.removed
+            .into_iter()
+            .flat_map(move |removed| cursor.read(removed))
+            .copied()
+    }
+}
+
+pub struct RemovedComponentsFetch<T> {
+    cursor: EventCursor<Entity>,
+    _data: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for RemovedComponentsFetch<T> {
+    fn default() -> Self {
+        Self {
+            cursor: EventCursor::default(),
+            _data: PhantomData,
+        }
+    }
+}
+
+// Removals are only logged with mutable access to the world, so reading them
+// can't conflict with any other system.
+impl<T: Component> SystemParam for RemovedComponents<'_, T> {
+    type Fetch = RemovedComponentsFetch<T>;
+    fn access(_access: &mut Access) {}
+}
+
+impl<'world_borrow, T: Component> SystemParamFetch<'world_borrow> for RemovedComponentsFetch<T> {
+    type Item = RemovedComponents<'world_borrow, T>;
+
+    fn fetch(
+        &'world_borrow mut self,
+        world: &'world_borrow World,
+        _system_ticks: SystemTicks,
+    ) -> Result<Self::Item, EcsError> {
+        Ok(RemovedComponents {
+            removed: world.removed_log::<T>(),
+            cursor: &mut self.cursor,
+            _data: PhantomData,
+        })
+    }
+}
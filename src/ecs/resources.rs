@@ -0,0 +1,248 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    cell::{Ref, RefCell, RefMut},
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    thread::{self, ThreadId},
+};
+
+use log::warn;
+use thiserror::Error;
+
+type Lock<T> = RwLock<T>;
+
+#[derive(Debug, Error)]
+pub enum ResourceError {
+    #[error("Resource does not exist")]
+    Missing,
+    #[error("Resource is already borrowed in a way that conflicts with this borrow")]
+    AlreadyBorrowed,
+}
+
+pub trait Resource: Send + Sync + 'static {}
+
+impl<T: Send + Sync + 'static> Resource for T {}
+
+/// Shared access to a resource, it stays read-locked until this is dropped.
+pub struct Res<'world_borrow, R> {
+    guard: RwLockReadGuard<'world_borrow, R>,
+}
+
+impl<R> Deref for Res<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.guard
+    }
+}
+
+/// Exclusive access to a resource, it stays write-locked until this is dropped.
+pub struct ResMut<'world_borrow, R> {
+    guard: RwLockWriteGuard<'world_borrow, R>,
+}
+
+impl<R> Deref for ResMut<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.guard
+    }
+}
+
+impl<R> DerefMut for ResMut<'_, R> {
+    fn deref_mut(&mut self) -> &mut R {
+        &mut self.guard
+    }
+}
+
+/// Singletons stored once per type, each behind its own lock.
+#[derive(Default)]
+pub(crate) struct Resources {
+    resources: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Resources {
+    fn get_lock<R: Resource>(&self) -> Result<&Lock<R>, ResourceError> {
+        self.resources
+            .get(&TypeId::of::<R>())
+            .and_then(|resource| resource.downcast_ref::<Lock<R>>())
+            .ok_or(ResourceError::Missing)
+    }
+
+    /// Inserts a resource, returning the previous one if there was any.
+    /// Resources poisoned by a system panicking while writing them are still handed back.
+    pub(crate) fn insert<R: Resource>(&mut self, resource: R) -> Option<R> {
+        self.resources
+            .insert(TypeId::of::<R>(), Box::new(Lock::new(resource)))
+            .and_then(|previous| previous.downcast::<Lock<R>>().ok())
+            .map(|previous| {
+                previous
+                    .into_inner()
+                    .unwrap_or_else(PoisonError::into_inner)
+            })
+    }
+
+    pub(crate) fn remove<R: Resource>(&mut self) -> Option<R> {
+        self.resources
+            .remove(&TypeId::of::<R>())
+            .and_then(|resource| resource.downcast::<Lock<R>>().ok())
+            .map(|resource| {
+                resource
+                    .into_inner()
+                    .unwrap_or_else(PoisonError::into_inner)
+            })
+    }
+
+    pub(crate) fn contains<R: Resource>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    pub(crate) fn get<R: Resource>(&self) -> Result<Res<'_, R>, ResourceError> {
+        let guard = self
+            .get_lock::<R>()?
+            .try_read()
+            .map_err(|_| ResourceError::AlreadyBorrowed)?;
+        Ok(Res { guard })
+    }
+
+    pub(crate) fn get_mut<R: Resource>(&self) -> Result<ResMut<'_, R>, ResourceError> {
+        let guard = self
+            .get_lock::<R>()?
+            .try_write()
+            .map_err(|_| ResourceError::AlreadyBorrowed)?;
+        Ok(ResMut { guard })
+    }
+}
+
+/// Shared access to a non-send resource, it stays borrowed until this is dropped.
+pub struct NonSend<'world_borrow, R> {
+    guard: Ref<'world_borrow, R>,
+}
+
+impl<R> Deref for NonSend<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.guard
+    }
+}
+
+/// Exclusive access to a non-send resource, it stays borrowed until this is dropped.
+pub struct NonSendMut<'world_borrow, R> {
+    guard: RefMut<'world_borrow, R>,
+}
+
+impl<R> Deref for NonSendMut<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.guard
+    }
+}
+
+impl<R> DerefMut for NonSendMut<'_, R> {
+    fn deref_mut(&mut self) -> &mut R {
+        &mut self.guard
+    }
+}
+
+struct NonSendResource {
+    /// Thread the resource was inserted on, the only one allowed to touch it.
+    thread: ThreadId,
+    /// A 'RefCell' of the resource.
+    value: Box<dyn Any>,
+}
+
+/// Resources that aren't 'Send' or 'Sync', like SDL textures or an asset store holding them.
+/// Each one can only be used from the thread that inserted it, using it from any other thread
+/// panics. Systems using them always run on the thread calling 'World::update'.
+#[derive(Default)]
+pub(crate) struct NonSendResources {
+    resources: HashMap<TypeId, NonSendResource>,
+}
+
+// Safety: resources are only ever accessed or dropped on the thread that inserted them,
+// which every method checks before touching them
+unsafe impl Send for NonSendResources {}
+unsafe impl Sync for NonSendResources {}
+
+impl NonSendResources {
+    fn check_thread<R: 'static>(resource: &NonSendResource) {
+        assert_eq!(
+            resource.thread,
+            thread::current().id(),
+            "Non-send resource {} used outside the thread that inserted it",
+            type_name::<R>()
+        );
+    }
+
+    fn get_cell<R: 'static>(&self) -> Result<&RefCell<R>, ResourceError> {
+        let resource = self
+            .resources
+            .get(&TypeId::of::<R>())
+            .ok_or(ResourceError::Missing)?;
+        Self::check_thread::<R>(resource);
+        Ok(resource.value.downcast_ref::<RefCell<R>>().unwrap())
+    }
+
+    /// Inserts a resource, returning the previous one if there was any.
+    pub(crate) fn insert<R: 'static>(&mut self, resource: R) -> Option<R> {
+        // Checked first, the previous one mustn't be dropped here if this panics
+        if let Some(previous) = self.resources.get(&TypeId::of::<R>()) {
+            Self::check_thread::<R>(previous);
+        }
+        let resource = NonSendResource {
+            thread: thread::current().id(),
+            value: Box::new(RefCell::new(resource)),
+        };
+        self.resources
+            .insert(TypeId::of::<R>(), resource)
+            .map(Self::into_inner)
+    }
+
+    pub(crate) fn remove<R: 'static>(&mut self) -> Option<R> {
+        let resource = self.resources.get(&TypeId::of::<R>())?;
+        Self::check_thread::<R>(resource);
+        self.resources
+            .remove(&TypeId::of::<R>())
+            .map(Self::into_inner)
+    }
+
+    fn into_inner<R: 'static>(resource: NonSendResource) -> R {
+        resource
+            .value
+            .downcast::<RefCell<R>>()
+            .unwrap()
+            .into_inner()
+    }
+
+    pub(crate) fn get<R: 'static>(&self) -> Result<NonSend<'_, R>, ResourceError> {
+        let guard = self
+            .get_cell::<R>()?
+            .try_borrow()
+            .map_err(|_| ResourceError::AlreadyBorrowed)?;
+        Ok(NonSend { guard })
+    }
+
+    pub(crate) fn get_mut<R: 'static>(&self) -> Result<NonSendMut<'_, R>, ResourceError> {
+        let guard = self
+            .get_cell::<R>()?
+            .try_borrow_mut()
+            .map_err(|_| ResourceError::AlreadyBorrowed)?;
+        Ok(NonSendMut { guard })
+    }
+}
+
+impl Drop for NonSendResources {
+    fn drop(&mut self) {
+        let current = thread::current().id();
+        for (_, resource) in self.resources.drain() {
+            if resource.thread != current {
+                // Dropping it here would be just as unsound as using it, so it's leaked
+                warn!("Leaking a non-send resource dropped outside the thread that inserted it");
+                std::mem::forget(resource);
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use super::{
+    archetype::Component,
+    change_detection::{ComponentTicks, Tick},
+    column::Column,
+    components::ComponentInfo,
+    entities::EntityId,
+};
+
+/// Values of a single component type, stored outside of archetypes and looked up by entity.
+/// Adding and removing them doesn't move the entity to another archetype, which makes them
+/// a good fit for components that are toggled often.
+pub(crate) struct ComponentSparseSet {
+    /// Values are packed, in no particular order.
+    dense: Column,
+    /// Entity owning each value of 'dense'.
+    entities: Vec<EntityId>,
+    /// Index in 'dense' of each entity's value, indexed by 'EntityId'.
+    sparse: Vec<Option<usize>>,
+}
+
+impl ComponentSparseSet {
+    pub(crate) fn new(info: &ComponentInfo) -> Self {
+        Self {
+            dense: Column::new(info),
+            entities: Vec::new(),
+            sparse: Vec::new(),
+        }
+    }
+
+    pub(crate) fn column(&self) -> &Column {
+        &self.dense
+    }
+
+    pub(crate) fn contains(&self, entity_id: EntityId) -> bool {
+        self.dense_index(entity_id).is_some()
+    }
+
+    /// Index of the entity's value in 'column', None if it has none.
+    pub(crate) fn dense_index(&self, entity_id: EntityId) -> Option<usize> {
+        self.sparse.get(entity_id as usize).copied().flatten()
+    }
+
+    /// Insert or overwrite the entity's value, returns true if it didn't have one.
+    pub(crate) fn insert<T: Component>(
+        &mut self,
+        entity_id: EntityId,
+        component: T,
+        tick: Tick,
+    ) -> bool {
+        if let Some(index) = self.dense_index(entity_id) {
+            self.dense.get_mut::<T>()[index] = component;
+            self.dense.ticks[index].set_changed(tick);
+            return false;
+        }
+        let sparse_index = entity_id as usize;
+        if sparse_index >= self.sparse.len() {
+            self.sparse.resize(sparse_index + 1, None);
+        }
+        self.sparse[sparse_index] = Some(self.entities.len());
+        self.entities.push(entity_id);
+        self.dense.push(component, ComponentTicks::new(tick));
+        true
+    }
+
+    /// Drop the entity's value, returns false if it had none.
+    pub(crate) fn remove(&mut self, entity_id: EntityId) -> bool {
+        let Some(index) = self.detach(entity_id) else {
+            return false;
+        };
+        self.dense.swap_remove(index);
+        true
+    }
+
+    /// Move the entity's value out, None if it had none.
+    pub(crate) fn take<T: Component>(&mut self, entity_id: EntityId) -> Option<T> {
+        let index = self.detach(entity_id)?;
+        // Safety: the value is read once and its row removed right away
+        unsafe {
+            let component = self.dense.read(index);
+            self.dense.swap_remove_forget(index);
+            Some(component)
+        }
+    }
+
+    /// Forget the entity, returning the index of its value which the caller has to swap_remove
+    /// from 'dense'.
+    fn detach(&mut self, entity_id: EntityId) -> Option<usize> {
+        let index = self
+            .sparse
+            .get_mut(entity_id as usize)
+            .and_then(Option::take)?;
+        self.entities.swap_remove(index);
+        // The last value will be moved into the removed one's place
+        if let Some(moved) = self.entities.get(index) {
+            self.sparse[*moved as usize] = Some(index);
+        }
+        Some(index)
+    }
+}
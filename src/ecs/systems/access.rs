@@ -0,0 +1,69 @@
+use std::{any::TypeId, collections::HashSet};
+
+use crate::ecs::{archetype::Component, resources::Resource};
+
+/// The components and resources a system reads and writes.
+/// Systems with compatible access can run at the same time.
+#[derive(Debug, Default, Clone)]
+pub struct Access {
+    exclusive: bool,
+    /// Set by systems using non-send resources, which have to run on the thread calling
+    /// 'World::update'.
+    non_send: bool,
+    component_reads: HashSet<TypeId>,
+    component_writes: HashSet<TypeId>,
+    resource_reads: HashSet<TypeId>,
+    resource_writes: HashSet<TypeId>,
+}
+
+impl Access {
+    /// Access that conflicts with every other system, used when a system doesn't declare any.
+    pub fn exclusive() -> Self {
+        Self {
+            exclusive: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn read_component<T: Component>(&mut self) {
+        self.component_reads.insert(TypeId::of::<T>());
+    }
+
+    pub fn write_component<T: Component>(&mut self) {
+        self.component_writes.insert(TypeId::of::<T>());
+    }
+
+    pub fn read_resource<R: Resource>(&mut self) {
+        self.resource_reads.insert(TypeId::of::<R>());
+    }
+
+    pub fn write_resource<R: Resource>(&mut self) {
+        self.resource_writes.insert(TypeId::of::<R>());
+    }
+
+    pub fn read_non_send<R: 'static>(&mut self) {
+        self.non_send = true;
+        self.resource_reads.insert(TypeId::of::<R>());
+    }
+
+    pub fn write_non_send<R: 'static>(&mut self) {
+        self.non_send = true;
+        self.resource_writes.insert(TypeId::of::<R>());
+    }
+
+    pub fn is_non_send(&self) -> bool {
+        self.non_send
+    }
+
+    /// Two accesses are compatible if neither writes anything the other one touches.
+    pub fn is_compatible(&self, other: &Access) -> bool {
+        !self.exclusive
+            && !other.exclusive
+            && self.component_writes.is_disjoint(&other.component_reads)
+            && self.component_writes.is_disjoint(&other.component_writes)
+            && other.component_writes.is_disjoint(&self.component_reads)
+            && self.resource_writes.is_disjoint(&other.resource_reads)
+            && self.resource_writes.is_disjoint(&other.resource_writes)
+            && other.resource_writes.is_disjoint(&self.resource_reads)
+    }
+}
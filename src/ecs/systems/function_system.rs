@@ -0,0 +1,117 @@
+use std::marker::PhantomData;
+
+use crate::ecs::{
+    change_detection::{SystemTicks, Tick},
+    world::{EcsError, World},
+};
+
+use super::{
+    access::Access,
+    system::System,
+    system_param::{SystemParam, SystemParamFetch},
+};
+
+/// Anything that can be registered with 'World::add_system'.
+/// 'Params' only exists to tell the implementations apart: '()' for types implementing
+/// 'System', 'fn(A, B, ..)' for functions taking 'SystemParam's.
+pub trait IntoSystem<Params> {
+    type System: System;
+
+    fn into_system(self) -> Self::System;
+}
+
+impl<S: System> IntoSystem<()> for S {
+    type System = S;
+
+    fn into_system(self) -> S {
+        self
+    }
+}
+
+/// A plain function whose arguments are fetched from the world on every run.
+/// 'State' holds the fetch of every argument between runs.
+pub struct FunctionSystem<F, Params, State> {
+    function: F,
+    access: Access,
+    state: State,
+    last_run: Tick,
+    _params: PhantomData<Params>,
+}
+
+macro_rules! function_system_impl {
+    ($($name:tt),*) => {
+        impl<Func, $($name: SystemParam + 'static),*> IntoSystem<fn($($name,)*)> for Func
+        where
+            Func: FnMut($($name),*)
+                + for<'a> FnMut($(<$name::Fetch as SystemParamFetch<'a>>::Item),*)
+                + Send
+                + Sync
+                + 'static,
+        {
+            type System = FunctionSystem<Func, fn($($name,)*), ($($name::Fetch,)*)>;
+
+            fn into_system(self) -> Self::System {
+                #[allow(unused_mut)]
+                let mut access = Access::default();
+                $($name::access(&mut access);)*
+                FunctionSystem {
+                    function: self,
+                    access,
+                    state: Default::default(),
+                    last_run: 0,
+                    _params: PhantomData,
+                }
+            }
+        }
+
+        impl<Func, $($name: SystemParam + 'static),*> System
+            for FunctionSystem<Func, fn($($name,)*), ($($name::Fetch,)*)>
+        where
+            Func: FnMut($($name),*)
+                + for<'a> FnMut($(<$name::Fetch as SystemParamFetch<'a>>::Item),*)
+                + Send
+                + Sync
+                + 'static,
+        {
+            fn label(&self) -> &str {
+                std::any::type_name::<Func>()
+            }
+
+            fn access(&self) -> Access {
+                self.access.clone()
+            }
+
+            #[allow(non_snake_case, unused_variables)]
+            fn run(&mut self, world: &World) -> Result<(), EcsError> {
+                // Passing through a generic function pins down which 'FnMut' impl gets called
+                #[allow(clippy::too_many_arguments)]
+                fn call<$($name),*>(mut function: impl FnMut($($name),*), $($name: $name),*) {
+                    function($($name),*)
+                }
+                let system_ticks = SystemTicks {
+                    last_run: self.last_run,
+                    this_run: world.increment_change_tick(),
+                };
+                let ($($name,)*) = &mut self.state;
+                $(let $name = $name.fetch(world, system_ticks)?;)*
+                call(&mut self.function, $($name),*);
+                self.last_run = system_ticks.this_run;
+                Ok(())
+            }
+        }
+    };
+}
+
+function_system_impl!();
+function_system_impl!(A);
+function_system_impl!(A, B);
+function_system_impl!(A, B, C);
+function_system_impl!(A, B, C, D);
+function_system_impl!(A, B, C, D, E);
+function_system_impl!(A, B, C, D, E, F);
+function_system_impl!(A, B, C, D, E, F, G);
+function_system_impl!(A, B, C, D, E, F, G, H);
+function_system_impl!(A, B, C, D, E, F, G, H, I);
+function_system_impl!(A, B, C, D, E, F, G, H, I, J);
+function_system_impl!(A, B, C, D, E, F, G, H, I, J, K);
+function_system_impl!(A, B, C, D, E, F, G, H, I, J, K, L);
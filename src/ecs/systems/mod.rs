@@ -0,0 +1,11 @@
+mod access;
+mod function_system;
+mod schedule;
+mod system;
+mod system_param;
+
+pub use access::Access;
+pub use function_system::IntoSystem;
+pub(crate) use schedule::Schedule;
+pub use system::*;
+pub use system_param::{SystemParam, SystemParamFetch};
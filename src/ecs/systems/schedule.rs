@@ -0,0 +1,140 @@
+use std::{any::Any, ops::Range, sync::Mutex};
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::ecs::world::{EcsError, World};
+
+use super::{access::Access, system::System};
+
+struct ScheduledSystem {
+    system: Box<dyn System>,
+    access: Access,
+}
+
+/// Systems registered in a 'World', kept in insertion order.
+/// The worker threads are started the first time a batch runs in parallel, and reused for
+/// every batch after that.
+#[derive(Default)]
+pub(crate) struct Schedule {
+    systems: Vec<ScheduledSystem>,
+    workers: Option<ThreadPool>,
+}
+
+impl Schedule {
+    pub(crate) fn add(&mut self, system: Box<dyn System>) {
+        let access = system.access();
+        self.systems.push(ScheduledSystem { system, access });
+    }
+
+    fn position<S: System>(&self) -> Option<usize> {
+        self.systems
+            .iter()
+            .position(|scheduled| (&*scheduled.system as &dyn Any).is::<S>())
+    }
+
+    fn position_by_label(&self, label: &str) -> Option<usize> {
+        self.systems
+            .iter()
+            .position(|scheduled| scheduled.system.label() == label)
+    }
+
+    pub(crate) fn remove<S: System>(&mut self) -> Option<S> {
+        let index = self.position::<S>()?;
+        let system: Box<dyn Any> = self.systems.remove(index).system;
+        system.downcast::<S>().ok().map(|system| *system)
+    }
+
+    pub(crate) fn remove_by_label(&mut self, label: &str) -> Option<Box<dyn System>> {
+        let index = self.position_by_label(label)?;
+        Some(self.systems.remove(index).system)
+    }
+
+    pub(crate) fn get<S: System>(&self) -> Option<&S> {
+        let system: &dyn Any = &*self.systems[self.position::<S>()?].system;
+        system.downcast_ref::<S>()
+    }
+
+    pub(crate) fn get_mut<S: System>(&mut self) -> Option<&mut S> {
+        let index = self.position::<S>()?;
+        let system: &mut dyn Any = &mut *self.systems[index].system;
+        system.downcast_mut::<S>()
+    }
+
+    pub(crate) fn get_by_label(&self, label: &str) -> Option<&dyn System> {
+        let index = self.position_by_label(label)?;
+        Some(&*self.systems[index].system)
+    }
+
+    /// Splits the systems into consecutive batches whose members don't conflict with each other.
+    /// A system that conflicts with anything in the current batch starts a new one, so
+    /// conflicting systems always run in the order they were added.
+    pub(crate) fn batches(&self) -> Vec<Range<usize>> {
+        let mut batches = Vec::new();
+        let mut start = 0;
+        for (index, scheduled) in self.systems.iter().enumerate() {
+            let conflicts = self.systems[start..index]
+                .iter()
+                .any(|other| !scheduled.access.is_compatible(&other.access));
+            if conflicts {
+                batches.push(start..index);
+                start = index;
+            }
+        }
+        if start < self.systems.len() {
+            batches.push(start..self.systems.len());
+        }
+        batches
+    }
+
+    /// Runs every batch in order, systems inside a batch run in parallel.
+    /// Stops at the first batch with an error.
+    pub(crate) fn run(&mut self, world: &World) -> Result<(), EcsError> {
+        for batch in self.batches() {
+            let batch = &mut self.systems[batch];
+            if batch.len() == 1 {
+                batch[0].system.run(world)?;
+            } else {
+                let workers = self.workers.get_or_insert_with(|| {
+                    ThreadPoolBuilder::new()
+                        .thread_name(|index| format!("ecs-worker-{index}"))
+                        .build()
+                        .expect("Failed to start the system worker threads")
+                });
+                run_parallel(workers, batch, world)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Hands a batch of systems to the schedule's worker threads and waits for all of them.
+/// Systems using non-send resources run on the calling thread in the meantime.
+fn run_parallel(
+    workers: &ThreadPool,
+    batch: &mut [ScheduledSystem],
+    world: &World,
+) -> Result<(), EcsError> {
+    let (local, shared): (Vec<_>, Vec<_>) = batch
+        .iter_mut()
+        .partition(|scheduled| scheduled.access.is_non_send());
+    let errors = Mutex::new(Vec::new());
+    workers.in_place_scope(|scope| {
+        for scheduled in shared {
+            let errors = &errors;
+            scope.spawn(move |_| {
+                if let Err(err) = scheduled.system.run(world) {
+                    errors.lock().unwrap().push(err);
+                }
+            });
+        }
+        for scheduled in local {
+            if let Err(err) = scheduled.system.run(world) {
+                errors.lock().unwrap().push(err);
+            }
+        }
+    });
+    match errors.into_inner().unwrap().into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
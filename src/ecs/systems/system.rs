@@ -0,0 +1,28 @@
+use std::any::Any;
+
+use crate::ecs::world::{EcsError, World};
+
+use super::access::Access;
+
+/// Time elapsed since the last 'World::update', in seconds.
+/// Inserted as a resource before systems run.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DeltaTime(pub f32);
+
+/// Logic that runs once per 'World::update', in the order systems were added.
+/// Systems only get shared access to the world, component and resource borrows are checked
+/// at runtime.
+/// Consecutive systems with compatible 'Access' run in parallel.
+pub trait System: Any + Send + Sync {
+    /// Identifies the system when looking it up by label, defaults to its type name.
+    fn label(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// What the system reads and writes, systems that don't declare it run on their own.
+    fn access(&self) -> Access {
+        Access::exclusive()
+    }
+
+    fn run(&mut self, world: &World) -> Result<(), EcsError>;
+}
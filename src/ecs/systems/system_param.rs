@@ -0,0 +1,168 @@
+use std::marker::PhantomData;
+
+use super::access::Access;
+use crate::ecs::{
+    change_detection::SystemTicks,
+    queries::{query, Query, QueryFetch, QueryFilter, QueryParameters},
+    resources::{NonSend, NonSendMut, Res, ResMut, Resource},
+    world::{EcsError, World},
+};
+
+/// Anything a function system can take as an argument.
+/// Like 'QueryParameter', the data is fetched through an inner trait so it can be requested
+/// for any lifetime.
+/// The fetch type is kept by the system between runs, so it doubles as per-system state.
+pub trait SystemParam {
+    type Fetch: for<'a> SystemParamFetch<'a> + Default + Send + Sync + 'static;
+    fn access(access: &mut Access);
+}
+
+pub trait SystemParamFetch<'world_borrow> {
+    type Item;
+
+    fn fetch(
+        &'world_borrow mut self,
+        world: &'world_borrow World,
+        system_ticks: SystemTicks,
+    ) -> Result<Self::Item, EcsError>;
+}
+
+impl<T: QueryParameters + 'static, F: QueryFilter + 'static> SystemParam for Query<'_, T, F> {
+    type Fetch = QueryFetch<T, F>;
+    fn access(access: &mut Access) {
+        <T as QueryParameters>::access(access);
+        <F as QueryFilter>::access(access);
+    }
+}
+
+impl<'world_borrow, T: QueryParameters, F: QueryFilter> SystemParamFetch<'world_borrow>
+    for QueryFetch<T, F>
+{
+    type Item = Query<'world_borrow, T, F>;
+
+    fn fetch(
+        &'world_borrow mut self,
+        world: &'world_borrow World,
+        system_ticks: SystemTicks,
+    ) -> Result<Self::Item, EcsError> {
+        query::<T, F>(world, system_ticks).map_err(EcsError::QueryErr)
+    }
+}
+
+pub struct ResFetch<R> {
+    _data: PhantomData<R>,
+}
+
+impl<R> Default for ResFetch<R> {
+    fn default() -> Self {
+        Self { _data: PhantomData }
+    }
+}
+
+pub struct ResMutFetch<R> {
+    _data: PhantomData<R>,
+}
+
+impl<R> Default for ResMutFetch<R> {
+    fn default() -> Self {
+        Self { _data: PhantomData }
+    }
+}
+
+impl<R: Resource> SystemParam for Res<'_, R> {
+    type Fetch = ResFetch<R>;
+    fn access(access: &mut Access) {
+        access.read_resource::<R>();
+    }
+}
+
+impl<'world_borrow, R: Resource> SystemParamFetch<'world_borrow> for ResFetch<R> {
+    type Item = Res<'world_borrow, R>;
+
+    fn fetch(
+        &'world_borrow mut self,
+        world: &'world_borrow World,
+        _system_ticks: SystemTicks,
+    ) -> Result<Self::Item, EcsError> {
+        world.resource::<R>()
+    }
+}
+
+impl<R: Resource> SystemParam for ResMut<'_, R> {
+    type Fetch = ResMutFetch<R>;
+    fn access(access: &mut Access) {
+        access.write_resource::<R>();
+    }
+}
+
+impl<'world_borrow, R: Resource> SystemParamFetch<'world_borrow> for ResMutFetch<R> {
+    type Item = ResMut<'world_borrow, R>;
+
+    fn fetch(
+        &'world_borrow mut self,
+        world: &'world_borrow World,
+        _system_ticks: SystemTicks,
+    ) -> Result<Self::Item, EcsError> {
+        world.resource_mut::<R>()
+    }
+}
+
+// Non-send resources are never sent anywhere by their fetch, so it only holds a function
+// pointer type to stay 'Send' and 'Sync'.
+pub struct NonSendFetch<R> {
+    _data: PhantomData<fn() -> R>,
+}
+
+impl<R> Default for NonSendFetch<R> {
+    fn default() -> Self {
+        Self { _data: PhantomData }
+    }
+}
+
+pub struct NonSendMutFetch<R> {
+    _data: PhantomData<fn() -> R>,
+}
+
+impl<R> Default for NonSendMutFetch<R> {
+    fn default() -> Self {
+        Self { _data: PhantomData }
+    }
+}
+
+impl<R: 'static> SystemParam for NonSend<'_, R> {
+    type Fetch = NonSendFetch<R>;
+    fn access(access: &mut Access) {
+        access.read_non_send::<R>();
+    }
+}
+
+impl<'world_borrow, R: 'static> SystemParamFetch<'world_borrow> for NonSendFetch<R> {
+    type Item = NonSend<'world_borrow, R>;
+
+    fn fetch(
+        &'world_borrow mut self,
+        world: &'world_borrow World,
+        _system_ticks: SystemTicks,
+    ) -> Result<Self::Item, EcsError> {
+        world.non_send_resource::<R>()
+    }
+}
+
+impl<R: 'static> SystemParam for NonSendMut<'_, R> {
+    type Fetch = NonSendMutFetch<R>;
+    fn access(access: &mut Access) {
+        access.write_non_send::<R>();
+    }
+}
+
+impl<'world_borrow, R: 'static> SystemParamFetch<'world_borrow> for NonSendMutFetch<R> {
+    type Item = NonSendMut<'world_borrow, R>;
+
+    fn fetch(
+        &'world_borrow mut self,
+        world: &'world_borrow World,
+        _system_ticks: SystemTicks,
+    ) -> Result<Self::Item, EcsError> {
+        world.non_send_resource_mut::<R>()
+    }
+}
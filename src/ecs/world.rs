@@ -1,7 +1,8 @@
-use std::any::TypeId;
-use std::cell::Ref;
 use std::collections::HashMap;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
 
+use log::debug;
 use log::warn;
 use thiserror::Error;
 
@@ -9,23 +10,57 @@ use super::archetype::Archetype;
 use super::archetype::ArchetypeError;
 use super::archetype::ArchetypeId;
 use super::archetype::Component;
-use super::archetype::ComponentStore;
-use super::bundles::calculate_bundle_id;
+use super::archetype::ComponentId;
 use super::bundles::BundleId;
 use super::bundles::ComponentBundle;
+use super::change_detection::SystemTicks;
+use super::change_detection::Tick;
+use super::change_detection::TAG_TICKS;
+use super::column::Column;
+use super::column::ColumnMut;
+use super::column::ColumnRef;
+use super::commands::CommandQueue;
+use super::commands::Commands;
+use super::component_refs::ComponentMut;
+use super::component_refs::ComponentRef;
+use super::component_refs::ComponentRefs;
+use super::components::ComponentRegistration;
+use super::components::Components;
 use super::entities::Entities;
 use super::entities::Entity;
 use super::entities::EntityArchetypeIndex;
 use super::entities::EntityError;
 use super::entities::EntityId;
 use super::entities::EntityLocation;
+use super::entity_refs::EntityMut;
+use super::entity_refs::EntityRef;
+use super::events::Event;
+use super::events::EventCursor;
+use super::events::Events;
 use super::helpers::index_twice;
+use super::hooks::ComponentHook;
+use super::hooks::ComponentHooks;
 use super::queries::query;
 use super::queries::FetchError;
 use super::queries::Query;
+use super::queries::QueryFilter;
 use super::queries::QueryParameters;
+use super::resources::NonSend;
+use super::resources::NonSendMut;
+use super::resources::NonSendResources;
+use super::resources::Res;
+use super::resources::ResMut;
+use super::resources::Resource;
+use super::resources::ResourceError;
+use super::resources::Resources;
+use super::sparse_set::ComponentSparseSet;
+use super::systems::DeltaTime;
+use super::systems::IntoSystem;
+use super::systems::Schedule;
+use super::systems::System;
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum EcsError {
     #[error("Archetype error: {0}")]
     ArchetypeErr(ArchetypeError),
@@ -33,20 +68,44 @@ pub enum EcsError {
     EntityErr(EntityError),
     #[error("Query error: {0}")]
     QueryErr(FetchError),
+    #[error("Resource error: {0}")]
+    ResourceErr(ResourceError),
 }
 
 pub struct World {
     entities: Entities,
     archetypes: Vec<Archetype>,
+    components: Components,
     bundle_to_archetype: HashMap<BundleId, ArchetypeId>,
+    resources: Resources,
+    non_send_resources: NonSendResources,
+    schedule: Schedule,
+    command_queue: CommandQueue,
+    event_updaters: Vec<fn(&World)>,
+    change_tick: AtomicU32,
+    last_change_tick: Tick,
+    removed_components: HashMap<ComponentId, Events<Entity>>,
+    hooks: HashMap<ComponentId, ComponentHooks>,
+    sparse_sets: HashMap<ComponentId, ComponentSparseSet>,
 }
 
 impl World {
-    fn new() -> Self {
+    pub fn new() -> Self {
         World {
             entities: Entities::default(),
             archetypes: Vec::new(),
+            components: Components::default(),
             bundle_to_archetype: HashMap::new(),
+            resources: Resources::default(),
+            non_send_resources: NonSendResources::default(),
+            schedule: Schedule::default(),
+            command_queue: CommandQueue::default(),
+            event_updaters: Vec::new(),
+            change_tick: AtomicU32::new(1),
+            last_change_tick: 0,
+            removed_components: HashMap::new(),
+            hooks: HashMap::new(),
+            sparse_sets: HashMap::new(),
         }
     }
 
@@ -54,6 +113,58 @@ impl World {
         self.archetypes.push(archetype);
     }
 
+    pub(crate) fn entities(&self) -> &Entities {
+        &self.entities
+    }
+
+    pub(crate) fn command_queue(&self) -> &CommandQueue {
+        &self.command_queue
+    }
+
+    /// The tick writes made right now are stamped with.
+    pub fn read_change_tick(&self) -> Tick {
+        self.change_tick.load(Ordering::Acquire)
+    }
+
+    /// Advance the world tick, returning the tick it was at.
+    pub(crate) fn increment_change_tick(&self) -> Tick {
+        self.change_tick.fetch_add(1, Ordering::AcqRel)
+    }
+
+    /// Ticks used by queries made outside of systems.
+    /// Changes are tracked since the end of the last update.
+    pub(crate) fn world_ticks(&self) -> SystemTicks {
+        SystemTicks {
+            last_run: self.last_change_tick,
+            this_run: self.read_change_tick(),
+        }
+    }
+
+    /// Every component type the world knows of.
+    pub fn components(&self) -> &Components {
+        &self.components
+    }
+
+    pub(crate) fn components_mut(&mut self) -> &mut Components {
+        &mut self.components
+    }
+
+    /// Sparse set of a component registered with 'ComponentRegistration::sparse_set'.
+    pub(crate) fn sparse_set(&self, component_id: ComponentId) -> Option<&ComponentSparseSet> {
+        self.sparse_sets.get(&component_id)
+    }
+
+    pub(crate) fn add_sparse_set(&mut self, component_id: ComponentId) {
+        let info = self.components.info(component_id);
+        self.sparse_sets
+            .entry(component_id)
+            .or_insert_with(|| ComponentSparseSet::new(info));
+    }
+
+    pub(crate) fn archetypes(&self) -> &[Archetype] {
+        &self.archetypes
+    }
+
     pub(crate) fn get_archetype(&self, archetype_id: ArchetypeId) -> &Archetype {
         &self.archetypes[archetype_id]
     }
@@ -62,8 +173,8 @@ impl World {
         &mut self.archetypes[archetype_id]
     }
 
-    pub(crate) fn get_bundle_archetype(&self, bundle_id: BundleId) -> Option<&ArchetypeId> {
-        self.bundle_to_archetype.get(&bundle_id)
+    pub(crate) fn get_bundle_archetype(&self, bundle_id: &[ComponentId]) -> Option<&ArchetypeId> {
+        self.bundle_to_archetype.get(bundle_id)
     }
 
     pub(crate) fn set_bundle_archetype(
@@ -86,22 +197,132 @@ impl World {
         self.get_archetype_mut(archetype_id).add_entity(entity_id)
     }
 
-    pub(crate) fn add_component_to_archetype<T: Component>(
+    /// Store a component of an entity being spawned into 'archetype_id'.
+    /// Sparse components go to their sparse set instead.
+    pub(crate) fn add_spawned_component<T: Component>(
         &mut self,
         archetype_id: ArchetypeId,
+        entity_id: EntityId,
+        component_id: ComponentId,
         component: T,
     ) {
+        let tick = self.read_change_tick();
+        if let Some(sparse_set) = self.sparse_sets.get_mut(&component_id) {
+            sparse_set.insert(entity_id, component, tick);
+            return;
+        }
         self.get_archetype_mut(archetype_id)
-            .add_entity_component::<T>(component)
+            .add_entity_component::<T>(component_id, component, tick)
+    }
+
+    /// Store a component of an entity that was already moved to an archetype with it.
+    /// 'added' tells whether the entity had it before, in which case it's overwritten.
+    pub(crate) fn insert_component_at<T: Component>(
+        &mut self,
+        entity_id: EntityId,
+        location: EntityLocation,
+        added: bool,
+        component: T,
+    ) {
+        let component_id = self.components.register::<T>();
+        let tick = self.read_change_tick();
+        // Sparse components don't move the entity, their set handles both cases
+        if let Some(sparse_set) = self.sparse_sets.get_mut(&component_id) {
+            sparse_set.insert(entity_id, component, tick);
+            return;
+        }
+        if !added {
+            self.set_component_in_archetype(&location, component_id, component);
+            return;
+        }
+        // The entity was just moved, so its row is the last one
+        debug_assert_eq!(
+            location.index_in_archetype + 1,
+            self.get_archetype(location.archetype_id).entities.len()
+        );
+        self.get_archetype_mut(location.archetype_id)
+            .add_entity_component(component_id, component, tick);
+    }
+
+    /// Move a component out of the entity at 'location'.
+    /// Sparse components are removed from their set, table components are read out of their
+    /// row without removing it.
+    /// # Safety
+    /// The entity must have the component, and its row must be removed with
+    /// 'Archetype::migrate_entity_forgetting' before the column is used again.
+    pub(crate) unsafe fn take_component_at<T: Component>(&mut self, location: EntityLocation) -> T {
+        let component_id = self.components.id::<T>().unwrap();
+        let archetype = &self.archetypes[location.archetype_id];
+        if let Some(sparse_set) = self.sparse_sets.get_mut(&component_id) {
+            let entity_id = archetype.entities[location.index_in_archetype];
+            return sparse_set.take(entity_id).unwrap();
+        }
+        match archetype.get_column(component_id) {
+            Some(column) => column.read(location.index_in_archetype),
+            // Tags aren't stored, but any zero-sized value can be read from a dangling pointer
+            None => std::ptr::NonNull::<T>::dangling().as_ptr().read(),
+        }
+    }
+
+    /// Components of the entity at 'location', sparse ones included, along with the column
+    /// holding each and the index of the entity's value in it.
+    /// Tags aren't stored, so they have no column.
+    fn entity_components(
+        &self,
+        location: EntityLocation,
+    ) -> impl Iterator<Item = (ComponentId, Option<(&Column, usize)>)> {
+        let archetype = self.get_archetype(location.archetype_id);
+        let entity_id = archetype.entities[location.index_in_archetype];
+        archetype
+            .component_ids()
+            .iter()
+            .map(move |component_id| {
+                let column = archetype.get_column(*component_id);
+                (
+                    *component_id,
+                    column.map(|column| (column, location.index_in_archetype)),
+                )
+            })
+            .chain(
+                self.sparse_sets
+                    .iter()
+                    .filter_map(move |(component_id, sparse_set)| {
+                        let index = sparse_set.dense_index(entity_id)?;
+                        Some((*component_id, Some((sparse_set.column(), index))))
+                    }),
+            )
+    }
+
+    /// Column holding the entity's 'T', along with the index of its value in it.
+    fn component_column<T: Component>(&self, location: EntityLocation) -> Option<(&Column, usize)> {
+        let component_id = self.components.id::<T>()?;
+        let archetype = self.get_archetype(location.archetype_id);
+        match self.sparse_sets.get(&component_id) {
+            Some(sparse_set) => {
+                let entity_id = archetype.entities[location.index_in_archetype];
+                Some((sparse_set.column(), sparse_set.dense_index(entity_id)?))
+            }
+            None => Some((
+                archetype.get_column(component_id)?,
+                location.index_in_archetype,
+            )),
+        }
     }
 
     pub(crate) fn set_component_in_archetype<T: Component>(
         &mut self,
         entity_location: &EntityLocation,
+        component_id: ComponentId,
         component: T,
     ) {
+        let tick = self.read_change_tick();
         self.get_archetype_mut(entity_location.archetype_id)
-            .set_entity_component(entity_location.index_in_archetype, component)
+            .set_entity_component(
+                component_id,
+                entity_location.index_in_archetype,
+                component,
+                tick,
+            )
             .unwrap();
     }
 
@@ -117,21 +338,279 @@ impl World {
         let entity = self.entities.allocate().unwrap();
         let location = bundle.spawn_in_world(self, entity.index);
         self.entities.set_location(entity.index, location).unwrap();
+        self.trigger_spawn_hooks(entity, location);
         entity
     }
 
-    pub fn remove(&mut self, entity: Entity) {
-        self.entities.deallocate(entity).unwrap();
+    /// Spawn an entity that was reserved through 'Commands::spawn'.
+    pub(crate) fn spawn_reserved(
+        &mut self,
+        entity: Entity,
+        bundle: impl ComponentBundle,
+    ) -> Result<(), EcsError> {
+        self.entities.claim(entity).map_err(EcsError::EntityErr)?;
+        let location = bundle.spawn_in_world(self, entity.index);
+        self.entities
+            .set_location(entity.index, location)
+            .map_err(EcsError::EntityErr)?;
+        self.trigger_spawn_hooks(entity, location);
+        Ok(())
+    }
+
+    /// Despawn an entity, dropping all of its components.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// let entity = world.spawn((456, true));
+    /// world.remove(entity).unwrap();
+    /// assert!(world.remove(entity).is_err());
+    /// ```
+    pub fn remove(&mut self, entity: Entity) -> Result<(), EcsError> {
+        let location = self
+            .entities
+            .location(entity)
+            .map_err(EcsError::EntityErr)?;
+        self.trigger_entity_hooks(entity, location, |hooks| &hooks.on_remove);
+        for (component_id, sparse_set) in self.sparse_sets.iter_mut() {
+            if sparse_set.remove(entity.index) {
+                self.removed_components
+                    .entry(*component_id)
+                    .or_default()
+                    .send(entity);
+            }
+        }
+        let archetype = &mut self.archetypes[location.archetype_id];
+        for component_id in archetype.component_ids() {
+            self.removed_components
+                .entry(*component_id)
+                .or_default()
+                .send(entity);
+        }
+        // Update moved entity location, if any
+        if let Some(moved) = archetype.remove_entity_with_components(location.index_in_archetype) {
+            self.entities
+                .set_location(moved, location)
+                .map_err(EcsError::EntityErr)?;
+        }
+        self.entities
+            .deallocate(entity)
+            .map_err(EcsError::EntityErr)
     }
 
     pub fn entity_count(&self) -> usize {
         self.entities.count()
     }
 
-    pub fn update(&mut self, dt: u32) {
-        // Setup stuff
-        // Run stuff
-        // Cleanup stuff
+    /// Run every registered system once, in the order they were added.
+    /// 'dt' is made available to systems through the 'DeltaTime' resource.
+    /// Commands recorded by the systems are applied once all of them ran, then event buffers
+    /// are swapped.
+    pub fn update(&mut self, dt: f32) -> Result<(), EcsError> {
+        self.insert_resource(DeltaTime(dt));
+        // Systems only need shared access, so they can be taken out while they run
+        let mut schedule = std::mem::take(&mut self.schedule);
+        let result = schedule.run(self);
+        self.schedule = schedule;
+        // Commands are stamped after the update ends, so queries made before the next one
+        // see what they changed
+        self.last_change_tick = self.increment_change_tick();
+        self.apply_commands();
+        for update_events in self.event_updaters.iter() {
+            update_events(self);
+        }
+        for removed in self.removed_components.values_mut() {
+            removed.update();
+        }
+        result
+    }
+
+    /// Register an event type, inserting its 'Events' resource.
+    /// Registered events are kept for two updates, then dropped.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// struct CollisionEvent(Entity, Entity);
+    /// let mut world = World::new();
+    /// world.add_event::<CollisionEvent>();
+    /// ```
+    pub fn add_event<E: Event>(&mut self) {
+        if self.has_resource::<Events<E>>() {
+            return;
+        }
+        self.insert_resource(Events::<E>::default());
+        self.event_updaters.push(|world| {
+            if let Ok(mut events) = world.resource_mut::<Events<E>>() {
+                events.update();
+            }
+        });
+    }
+
+    /// Send an event without going through an 'EventWriter'.
+    pub fn send_event<E: Event>(&self, event: E) -> Result<(), EcsError> {
+        self.resource_mut::<Events<E>>()?.send(event);
+        Ok(())
+    }
+
+    /// Entities that lost a 'T' during this update or the previous one.
+    /// Systems should use 'RemovedComponents' instead, to only see each removal once.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// let entity = world.spawn((456, true));
+    /// world.remove_component::<bool>(entity).unwrap();
+    /// assert_eq!(world.removed::<bool>().collect::<Vec<_>>(), vec![entity]);
+    /// ```
+    pub fn removed<T: Component>(&self) -> impl Iterator<Item = Entity> + '_ {
+        let mut cursor = EventCursor::default();
+        self.removed_log::<T>()
+            .into_iter()
+            .flat_map(move |removed| cursor.read(removed))
+            .copied()
+    }
+
+    pub(crate) fn removed_log<T: Component>(&self) -> Option<&Events<Entity>> {
+        self.removed_components.get(&self.components.id::<T>()?)
+    }
+
+    /// Record structural changes without mutable access to the world.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// let entity = world.commands().spawn((456, true));
+    /// world.apply_commands();
+    /// assert!(world.has_component::<bool>(entity));
+    /// ```
+    pub fn commands(&self) -> Commands<'_> {
+        Commands::new(self)
+    }
+
+    /// Apply every queued command in the order it was recorded.
+    /// Failing commands don't stop the rest from being applied.
+    /// Commands queued while applying, e.g. by component hooks, are applied as well.
+    pub fn apply_commands(&mut self) {
+        loop {
+            let commands = self.command_queue.take();
+            if commands.is_empty() {
+                break;
+            }
+            for command in commands {
+                if let Err(err) = command(self) {
+                    warn!("Command failed: {}", err);
+                }
+            }
+        }
+        self.entities.free_unclaimed();
+    }
+
+    /// Get the hooks of 'T', to register callbacks run when it is added, inserted or removed.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// struct Name(&'static str);
+    /// let mut world = World::new();
+    /// world.component_hooks::<Name>().on_add(|world, entity| {
+    ///     let name = world.get_component::<Name>(entity).unwrap();
+    ///     println!("{} spawned", name.0);
+    /// });
+    /// world.spawn((Name("Link"),));
+    /// ```
+    pub fn component_hooks<T: Component>(&mut self) -> &mut ComponentHooks {
+        let component_id = self.components.register::<T>();
+        self.hooks.entry(component_id).or_default()
+    }
+
+    /// Register 'T' ahead of time, to attach metadata to it.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// #[derive(Debug)]
+    /// struct Health(u32);
+    /// let mut world = World::new();
+    /// let id = world
+    ///     .register_component::<Health>()
+    ///     .serialize_with(|health| format!("{:?}", health))
+    ///     .id();
+    /// assert!(world.components().info(id).name().ends_with("Health"));
+    /// ```
+    pub fn register_component<T: Component>(&mut self) -> ComponentRegistration<'_, T> {
+        let component_id = self.components.register::<T>();
+        ComponentRegistration::new(self, component_id)
+    }
+
+    /// Name of every component of an entity, along with its serialized value for the components
+    /// registered with 'ComponentRegistration::serialize_with'.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// world.register_component::<i32>().serialize_with(|n| n.to_string());
+    /// let entity = world.spawn((456, true));
+    /// let components = world.inspect(entity).unwrap();
+    /// assert!(components.contains(&("i32", Some(String::from("456")))));
+    /// assert!(components.contains(&("bool", None)));
+    /// ```
+    pub fn inspect(&self, entity: Entity) -> Result<Vec<(&'static str, Option<String>)>, EcsError> {
+        let location = self
+            .entities
+            .location(entity)
+            .map_err(EcsError::EntityErr)?;
+        self.entity_components(location)
+            .map(|(component_id, column)| {
+                let info = self.components.info(component_id);
+                if !info.is_serializable() {
+                    return Ok((info.name(), None));
+                }
+                let Some((column, index)) = column else {
+                    // Safety: tags are zero-sized, so any aligned pointer points to a valid one
+                    let tag = std::ptr::without_provenance(info.layout().align());
+                    return Ok((info.name(), unsafe { info.serialize(tag) }));
+                };
+                // Safety: the column stores values of the type 'info' describes
+                column
+                    .try_read_raw(index, |component| unsafe {
+                        (info.name(), info.serialize(component))
+                    })
+                    .ok_or(EcsError::QueryErr(FetchError::AlreadyBorrowed))
+            })
+            .collect()
+    }
+
+    fn trigger_hook(
+        &self,
+        entity: Entity,
+        component_id: ComponentId,
+        hook: fn(&ComponentHooks) -> &Option<ComponentHook>,
+    ) {
+        if let Some(hook) = self
+            .hooks
+            .get(&component_id)
+            .and_then(|hooks| hook(hooks).as_ref())
+        {
+            hook(self, entity);
+        }
+    }
+
+    /// Trigger the hook of every component of the entity.
+    fn trigger_entity_hooks(
+        &self,
+        entity: Entity,
+        location: EntityLocation,
+        hook: fn(&ComponentHooks) -> &Option<ComponentHook>,
+    ) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        for (component_id, _) in self.entity_components(location) {
+            self.trigger_hook(entity, component_id, hook);
+        }
+    }
+
+    fn trigger_spawn_hooks(&self, entity: Entity, location: EntityLocation) {
+        self.trigger_entity_hooks(entity, location, |hooks| &hooks.on_add);
+        self.trigger_entity_hooks(entity, location, |hooks| &hooks.on_insert);
     }
 
     /// Add a single component to an entity.
@@ -148,99 +627,221 @@ impl World {
         entity: Entity,
         component: T,
     ) -> Result<(), EcsError> {
-        if let Some(entry) = self.entities.live_at_index(entity.index).copied() {
-            let type_id = TypeId::of::<T>();
-            let current_type_ids = self
-                .get_archetype(entry.location.archetype_id)
-                .components
-                .values()
-                .map(|comp_store| comp_store.type_id)
-                .collect::<Vec<TypeId>>();
-            let binary_search_index = current_type_ids.binary_search(&type_id);
-
-            if binary_search_index.is_ok() {
-                // Component already exists, just overwrite
-                self.set_component_in_archetype(&entry.location, component);
-                return Ok(());
+        let location = self
+            .entities
+            .location(entity)
+            .map_err(EcsError::EntityErr)?;
+        self.add_component_at(entity, location, component)?;
+        Ok(())
+    }
+
+    /// 'add_component' for an entity whose location is already known.
+    /// Returns the entity's new location.
+    pub(crate) fn add_component_at<T: Component>(
+        &mut self,
+        entity: Entity,
+        location: EntityLocation,
+        component: T,
+    ) -> Result<EntityLocation, EcsError> {
+        let component_id = self.components.register::<T>();
+        if self.sparse_sets.contains_key(&component_id) {
+            self.add_sparse_component(entity, component_id, component);
+            return Ok(location);
+        }
+        let old_archetype_idx = location.archetype_id;
+        if self
+            .get_archetype(old_archetype_idx)
+            .has_component_id(component_id)
+        {
+            // Component already exists, just overwrite
+            self.set_component_in_archetype(&location, component_id, component);
+            self.trigger_hook(entity, component_id, |hooks| &hooks.on_insert);
+            return Ok(location);
+        }
+
+        // Component does not exist in the current archetype
+        // Transitions are cached on the archetype, so the target only has to be found once
+        let new_archetype_idx = match self
+            .get_archetype(old_archetype_idx)
+            .add_edges
+            .get(&component_id)
+        {
+            Some(idx) => *idx,
+            None => {
+                let idx = self.find_archetype_with(old_archetype_idx, component_id);
+                self.get_archetype_mut(old_archetype_idx)
+                    .add_edges
+                    .insert(component_id, idx);
+                self.get_archetype_mut(idx)
+                    .remove_edges
+                    .insert(component_id, old_archetype_idx);
+                idx
             }
+        };
 
-            // Component does not exist in the current archetype
-            // We'll find one with the right combination or create a new one
-            let insert_index = binary_search_index.unwrap_or_else(|i| i);
-            let mut new_type_ids = current_type_ids.clone();
-            new_type_ids.insert(insert_index, type_id);
-            let bundle_id = calculate_bundle_id(&new_type_ids);
-            let new_archetype_idx = if let Some(idx) = self.bundle_to_archetype.get(&bundle_id) {
-                // Found matching archetype
-                *idx
-            } else {
-                // Didn't find matching archetype, let's create a new one
-                let mut new_archetype = Archetype::default();
-                for c in self
-                    .get_archetype_mut(entry.location.archetype_id)
-                    .components
-                    .values()
-                {
-                    new_archetype.components.insert(c.type_id, c.empty_clone());
-                }
-                let new_archetype_index = self.archetypes.len();
-                new_archetype
-                    .components
-                    .insert(type_id, ComponentStore::new::<T>());
-                self.set_bundle_archetype(bundle_id, new_archetype_index);
-                self.add_archetype(new_archetype);
-                println!("we're creating a new archetype: {}", new_archetype_index);
-                new_archetype_index
-            };
-
-            println!("old archetype: {}", entry.location.archetype_id);
-            // Split borrowing
-            let (old_archetype, new_archetype) = index_twice(
-                &mut self.archetypes,
-                entry.location.archetype_id,
-                new_archetype_idx,
-            );
+        let tick = self.read_change_tick();
+        let new_location = self.move_entity(entity, location, new_archetype_idx)?;
+        self.get_archetype_mut(new_archetype_idx)
+            .add_entity_component(component_id, component, tick);
+        self.trigger_hook(entity, component_id, |hooks| &hooks.on_add);
+        self.trigger_hook(entity, component_id, |hooks| &hooks.on_insert);
+        Ok(new_location)
+    }
+
+    /// Sparse components don't change the entity's archetype, so no migration is needed.
+    fn add_sparse_component<T: Component>(
+        &mut self,
+        entity: Entity,
+        component_id: ComponentId,
+        component: T,
+    ) {
+        let tick = self.read_change_tick();
+        let added =
+            self.sparse_sets
+                .get_mut(&component_id)
+                .unwrap()
+                .insert(entity.index, component, tick);
+        if added {
+            self.trigger_hook(entity, component_id, |hooks| &hooks.on_add);
+        }
+        self.trigger_hook(entity, component_id, |hooks| &hooks.on_insert);
+    }
+
+    fn take_sparse_component<T: Component>(
+        &mut self,
+        entity: Entity,
+        component_id: ComponentId,
+    ) -> Result<T, EcsError> {
+        if !self.sparse_sets[&component_id].contains(entity.index) {
+            return Err(EcsError::ArchetypeErr(ArchetypeError::ComponentMissing));
+        }
+        // Hooks get to read the component one last time
+        self.trigger_hook(entity, component_id, |hooks| &hooks.on_remove);
+        let component = self
+            .sparse_sets
+            .get_mut(&component_id)
+            .unwrap()
+            .take(entity.index)
+            .unwrap();
+        self.removed_components
+            .entry(component_id)
+            .or_default()
+            .send(entity);
+        Ok(component)
+    }
+
+    /// Move an entity to another archetype, dropping the components it doesn't have.
+    /// Returns the entity's new location.
+    fn move_entity(
+        &mut self,
+        entity: Entity,
+        location: EntityLocation,
+        new_archetype_id: ArchetypeId,
+    ) -> Result<EntityLocation, EcsError> {
+        self.move_entity_with(
+            entity,
+            location,
+            new_archetype_id,
+            Archetype::migrate_entity,
+        )
+    }
+
+    /// Like 'move_entity', but the components the new archetype doesn't have are forgotten
+    /// instead of dropped.
+    /// # Safety
+    /// Those components must have been moved out with 'take_component_at' already.
+    unsafe fn move_entity_forgetting(
+        &mut self,
+        entity: Entity,
+        location: EntityLocation,
+        new_archetype_id: ArchetypeId,
+    ) -> Result<EntityLocation, EcsError> {
+        self.move_entity_with(entity, location, new_archetype_id, |old, index, new| {
+            // Safety: guaranteed by the caller
+            unsafe { old.migrate_entity_forgetting(index, new) }
+        })
+    }
+
+    fn move_entity_with(
+        &mut self,
+        entity: Entity,
+        location: EntityLocation,
+        new_archetype_id: ArchetypeId,
+        migrate: impl FnOnce(&mut Archetype, EntityArchetypeIndex, &mut Archetype),
+    ) -> Result<EntityLocation, EcsError> {
+        // Basically we're going through this checklist:
+        // Add entity to new archetype
+        // Update current entity location
+        // Migrate components to new archetype
+        // Remove entity from current archetype
+        // Update moved entity location, if any
+        let (old_archetype, new_archetype) = index_twice(
+            &mut self.archetypes,
+            location.archetype_id,
+            new_archetype_id,
+        );
 
-            // Basically we're going through this checklist:
-            // Add entity to new archetype
-            // Update current entity location
-            // Migrate components to new archetype
-            // Add new component to new archetype too
-            // Remove entity from current archetype
-            // Update moved entity location, if any
+        // Pushes to entity vec, adds space to component sets
+        let new_location =
+            EntityLocation::new(new_archetype_id, new_archetype.add_entity(entity.index));
+        self.entities
+            .set_location(entity.index, new_location)
+            .map_err(EcsError::EntityErr)?;
 
-            // Pushes to entity vec, adds space to component sets
-            let new_idx_in_archetype = new_archetype.add_entity(entity.index);
+        migrate(old_archetype, location.index_in_archetype, new_archetype);
+
+        // We get None if we're last
+        if let Some(moved) = old_archetype.remove_entity(location.index_in_archetype) {
             self.entities
-                .set_location(
-                    entity.index,
-                    EntityLocation::new(new_archetype_idx, new_idx_in_archetype),
-                )
+                .set_location(moved, location)
                 .map_err(EcsError::EntityErr)?;
+        }
+        Ok(new_location)
+    }
 
-            // Migrate components to new archetype
-            for type_id in current_type_ids {
-                old_archetype.migrate_component(
-                    type_id,
-                    entry.location.index_in_archetype,
-                    new_archetype,
-                );
-            }
+    /// Find the archetype with the components of 'archetype_id' plus 'component_id', creating it
+    /// if needed.
+    fn find_archetype_with(
+        &mut self,
+        archetype_id: ArchetypeId,
+        component_id: ComponentId,
+    ) -> ArchetypeId {
+        let mut new_component_ids = self.get_archetype(archetype_id).component_ids().to_vec();
+        new_component_ids.push(component_id);
+        new_component_ids.sort_unstable();
+        self.find_or_create_archetype(new_component_ids)
+    }
 
-            // Add new component too
-            new_archetype.add_entity_component(component);
+    /// Find the archetype with the components of 'archetype_id' minus 'component_id', creating
+    /// it if needed.
+    fn find_archetype_without(
+        &mut self,
+        archetype_id: ArchetypeId,
+        component_id: ComponentId,
+    ) -> ArchetypeId {
+        let new_component_ids = self
+            .get_archetype(archetype_id)
+            .component_ids()
+            .iter()
+            .copied()
+            .filter(|other| *other != component_id)
+            .collect::<Vec<_>>();
+        self.find_or_create_archetype(new_component_ids)
+    }
 
-            // Update moved entity location, if any
-            // We return None if we're last
-            if let Some(moved) = old_archetype.remove_entity(entry.location.index_in_archetype) {
-                self.entities
-                    .set_location(moved, entry.location)
-                    .map_err(EcsError::EntityErr)?;
-            }
-            Ok(())
-        } else {
-            Err(EcsError::EntityErr(EntityError::DoesNotExist))
+    /// 'component_ids' must be sorted.
+    fn find_or_create_archetype(&mut self, component_ids: Vec<ComponentId>) -> ArchetypeId {
+        if let Some(idx) = self.get_bundle_archetype(&component_ids) {
+            // Found matching archetype
+            return *idx;
         }
+        // Didn't find matching archetype, let's create a new one
+        let archetype = Archetype::new(component_ids.clone(), &self.components);
+        let new_archetype_index = self.archetypes.len();
+        self.set_bundle_archetype(component_ids.into(), new_archetype_index);
+        self.add_archetype(archetype);
+        debug!("Created archetype {}", new_archetype_index);
+        new_archetype_index
     }
 
     /// Remove a single component from an entity.
@@ -251,135 +852,569 @@ impl World {
     /// let mut world = World::new();
     /// let entity = world.spawn((456, true));
     /// let b = world.remove_component::<bool>(entity).unwrap();
+    /// assert!(b);
     /// ```
-    pub fn remove_component<T: Component>(&mut self, entity: Entity) -> Result<(), EcsError> {
-        if let Some(entry) = self.entities.live_at_index(entity.index).copied() {
-            let type_id = TypeId::of::<T>();
-            let current_type_ids = self
-                .get_archetype(entry.location.archetype_id)
-                .components
-                .values()
-                .map(|comp_store| comp_store.type_id)
-                .collect::<Vec<TypeId>>();
-
-            let type_id_idx = current_type_ids.binary_search(&type_id);
-            if type_id_idx.is_err() {
-                // Component doesn't exist in archetype?!
-                return Err(EcsError::ArchetypeErr(ArchetypeError::ComponentMissing));
+    pub fn remove_component<T: Component>(&mut self, entity: Entity) -> Result<T, EcsError> {
+        let location = self
+            .entities
+            .location(entity)
+            .map_err(EcsError::EntityErr)?;
+        self.remove_component_at(entity, location)
+            .map(|(component, _)| component)
+    }
+
+    /// 'remove_component' for an entity whose location is already known.
+    /// Returns the component along with the entity's new location.
+    pub(crate) fn remove_component_at<T: Component>(
+        &mut self,
+        entity: Entity,
+        location: EntityLocation,
+    ) -> Result<(T, EntityLocation), EcsError> {
+        let old_archetype_idx = location.archetype_id;
+        if let Some(component_id) = self
+            .components
+            .id::<T>()
+            .filter(|component_id| self.sparse_sets.contains_key(component_id))
+        {
+            let component = self.take_sparse_component(entity, component_id)?;
+            return Ok((component, location));
+        }
+        let component_id = match self.components.id::<T>() {
+            Some(component_id)
+                if self
+                    .get_archetype(old_archetype_idx)
+                    .has_component_id(component_id) =>
+            {
+                component_id
             }
+            // Component doesn't exist in archetype?!
+            _ => return Err(EcsError::ArchetypeErr(ArchetypeError::ComponentMissing)),
+        };
+        // Hooks get to read the component one last time
+        self.trigger_hook(entity, component_id, |hooks| &hooks.on_remove);
 
-            let mut new_type_ids = current_type_ids.clone();
-            new_type_ids.remove(type_id_idx.unwrap());
-            let bundle_id = calculate_bundle_id(&new_type_ids);
-            let new_archetype_idx = if let Some(idx) = self.bundle_to_archetype.get(&bundle_id) {
-                // Found matching archetype
-                *idx
-            } else {
-                // Didn't find matching archetype, let's create a new one
-                let mut new_archetype = Archetype::default();
-                for c in self
-                    .get_archetype_mut(entry.location.archetype_id)
-                    .components
-                    .values()
-                {
-                    new_archetype.components.insert(c.type_id, c.empty_clone());
-                }
-                let new_archetype_index = self.archetypes.len();
-                new_archetype
-                    .components
-                    .insert(type_id, ComponentStore::new::<T>());
-                self.set_bundle_archetype(bundle_id, new_archetype_index);
-                self.add_archetype(new_archetype);
-                new_archetype_index
-            };
-
-            // Basically we're going through this checklist:
-            // Add entity to new archetype
-            // Update current entity location
-            // Migrate components to new archetype, except removed component
-            // Remove entity from current archetype
-            // Update moved entity location, if any
-
-            let (old_archetype, new_archetype) = index_twice(
-                &mut self.archetypes,
-                entry.location.archetype_id,
-                new_archetype_idx,
-            );
+        let new_archetype_idx = match self
+            .get_archetype(old_archetype_idx)
+            .remove_edges
+            .get(&component_id)
+        {
+            Some(idx) => *idx,
+            None => {
+                let idx = self.find_archetype_without(old_archetype_idx, component_id);
+                self.get_archetype_mut(old_archetype_idx)
+                    .remove_edges
+                    .insert(component_id, idx);
+                self.get_archetype_mut(idx)
+                    .add_edges
+                    .insert(component_id, old_archetype_idx);
+                idx
+            }
+        };
 
-            // Pushes into entity vec, adds space to component sets
-            let new_idx_in_archetype = new_archetype.add_entity(entity.index);
-            self.entities
-                .set_location(
-                    entity.index,
-                    EntityLocation::new(new_archetype_idx, new_idx_in_archetype),
-                )
-                .map_err(EcsError::EntityErr)?;
+        // Safety: the entity has the component, and its row is forgotten right below
+        let component = unsafe { self.take_component_at::<T>(location) };
+        let new_location =
+            unsafe { self.move_entity_forgetting(entity, location, new_archetype_idx)? };
+        self.removed_components
+            .entry(component_id)
+            .or_default()
+            .send(entity);
+        Ok((component, new_location))
+    }
 
-            // Migrate components to new archetype, except removed one
-            for type_id in new_type_ids {
-                old_archetype.migrate_component(
-                    type_id,
-                    entry.location.index_in_archetype,
-                    new_archetype,
-                );
-            }
+    /// Move a single component out of an entity, to hand it to another one.
+    /// Same as 'remove_component', which also returns the component.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// let tank = world.spawn((456, String::from("Cannon")));
+    /// let weapon = world.take_component::<String>(tank).unwrap();
+    /// let pickup = world.spawn((weapon,));
+    /// ```
+    pub fn take_component<T: Component>(&mut self, entity: Entity) -> Result<T, EcsError> {
+        self.remove_component(entity)
+    }
 
-            if let Some(moved) = old_archetype.remove_entity(entry.location.index_in_archetype) {
-                self.entities
-                    .set_location(moved, entry.location)
-                    .map_err(EcsError::EntityErr)?;
-            }
-            Ok(())
+    /// Add several components to an entity, moving it to another archetype at most once.
+    /// Components the entity already has are overwritten.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// let entity = world.spawn((456,));
+    /// world.insert_bundle(entity, (true, String::from("Name"))).unwrap();
+    /// ```
+    pub fn insert_bundle<B: ComponentBundle>(
+        &mut self,
+        entity: Entity,
+        bundle: B,
+    ) -> Result<(), EcsError> {
+        let location = self
+            .entities
+            .location(entity)
+            .map_err(EcsError::EntityErr)?;
+        let component_ids = B::component_ids(&mut self.components);
+        let archetype = self.get_archetype(location.archetype_id);
+        let added = component_ids
+            .iter()
+            .map(|component_id| match self.sparse_sets.get(component_id) {
+                Some(sparse_set) => !sparse_set.contains(entity.index),
+                None => !archetype.has_component_id(*component_id),
+            })
+            .collect::<Vec<_>>();
+        // Sparse components don't change the entity's archetype
+        let mut new_component_ids = archetype.component_ids().to_vec();
+        new_component_ids.extend(
+            component_ids
+                .iter()
+                .zip(&added)
+                .filter(|(component_id, added)| {
+                    **added && !self.sparse_sets.contains_key(component_id)
+                })
+                .map(|(component_id, _)| *component_id),
+        );
+        let location = if new_component_ids.len() == archetype.component_ids().len() {
+            location
         } else {
-            Err(EcsError::EntityErr(EntityError::DoesNotExist))
+            new_component_ids.sort_unstable();
+            debug_assert!(
+                new_component_ids.windows(2).all(|x| x[0] != x[1]),
+                "'ComponentBundles' can't have duplicate types"
+            );
+            let new_archetype_id = self.find_or_create_archetype(new_component_ids);
+            self.move_entity(entity, location, new_archetype_id)?
+        };
+        bundle.insert_in_world(self, entity.index, location, &added);
+
+        for (component_id, added) in component_ids.iter().zip(&added) {
+            if *added {
+                self.trigger_hook(entity, *component_id, |hooks| &hooks.on_add);
+            }
+        }
+        for component_id in &component_ids {
+            self.trigger_hook(entity, *component_id, |hooks| &hooks.on_insert);
         }
+        Ok(())
     }
 
-    pub fn has_component<T: Component>(&self, entity: Entity) -> bool {
-        if let Some(entry) = self.entities.live_at_index(entity.index).copied() {
-            let archetype = self.get_archetype(entry.location.archetype_id);
-            return archetype.has_component::<T>();
+    /// Remove several components from an entity, moving it to another archetype at most once.
+    /// If the entity lacks any of them nothing is removed, otherwise they're returned.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// let entity = world.spawn((456, true, String::from("Name")));
+    /// let (b, name) = world.remove_bundle::<(bool, String)>(entity).unwrap();
+    /// ```
+    pub fn remove_bundle<B: ComponentBundle>(&mut self, entity: Entity) -> Result<B, EcsError> {
+        let location = self
+            .entities
+            .location(entity)
+            .map_err(EcsError::EntityErr)?;
+        let component_ids = B::component_ids(&mut self.components);
+        let archetype = self.get_archetype(location.archetype_id);
+        let has_all =
+            component_ids
+                .iter()
+                .all(|component_id| match self.sparse_sets.get(component_id) {
+                    Some(sparse_set) => sparse_set.contains(entity.index),
+                    None => archetype.has_component_id(*component_id),
+                });
+        if !has_all {
+            return Err(EcsError::ArchetypeErr(ArchetypeError::ComponentMissing));
+        }
+        let new_component_ids = archetype
+            .component_ids()
+            .iter()
+            .copied()
+            .filter(|component_id| !component_ids.contains(component_id))
+            .collect::<Vec<_>>();
+        // Hooks get to read the components one last time
+        for component_id in &component_ids {
+            self.trigger_hook(entity, *component_id, |hooks| &hooks.on_remove);
+        }
+
+        let new_archetype_id = self.find_or_create_archetype(new_component_ids);
+        // Safety: the entity has every component, and the rows read from are forgotten right
+        // below, before anything else can touch them
+        let bundle = unsafe { B::take_from_world(self, location) };
+        if new_archetype_id != location.archetype_id {
+            // Safety: the components the new archetype doesn't have were just taken
+            unsafe { self.move_entity_forgetting(entity, location, new_archetype_id)? };
+        }
+        for component_id in component_ids {
+            self.removed_components
+                .entry(component_id)
+                .or_default()
+                .send(entity);
         }
-        false
+        Ok(bundle)
     }
 
-    pub fn query<'world_borrow, T: QueryParameters>(
-        &'world_borrow self,
-    ) -> Result<Query<'world_borrow, T>, EcsError> {
-        Ok(query::<T>(self)
-            .map_err(EcsError::QueryErr)?
-            .take()
-            .unwrap())
+    /// Handle to a single entity, to read its components without looking it up on every call.
+    /// # Panics
+    /// If the entity was despawned, see 'get_entity' for a fallible version.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// let entity = world.spawn((456, true));
+    /// let entity = world.entity(entity);
+    /// assert!(entity.contains::<bool>());
+    /// assert_eq!(*entity.get::<i32>().unwrap(), 456);
+    /// ```
+    pub fn entity(&self, entity: Entity) -> EntityRef<'_> {
+        self.get_entity(entity)
+            .expect("Entity handles need a live entity")
     }
 
-    // pub fn add_system<T: SystemFn>(&mut self, system: T) {}
-    // pub fn remove_system<T: SystemFn>(&mut self) -> Option<System> {}
-    pub fn has_system() {}
-    pub fn get_system() {}
-}
+    /// Handle to a single entity, None if it was despawned.
+    pub fn get_entity(&self, entity: Entity) -> Option<EntityRef<'_>> {
+        let location = self.entities.location(entity).ok()?;
+        Some(EntityRef::new(self, entity, location))
+    }
 
-mod tests {
-    #[allow(unused_imports)]
-    use super::*;
+    /// Handle to a single entity, to read and change its components without looking it up on
+    /// every call.
+    /// # Panics
+    /// If the entity was despawned, see 'get_entity_mut' for a fallible version.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// let entity = world.spawn((456,));
+    /// world
+    ///     .entity_mut(entity)
+    ///     .insert(true)
+    ///     .insert(String::from("Name"));
+    /// ```
+    pub fn entity_mut(&mut self, entity: Entity) -> EntityMut<'_> {
+        self.get_entity_mut(entity)
+            .expect("Entity handles need a live entity")
+    }
 
-    #[test]
-    fn can_create_entity() {
-        let mut world = World::new();
-        let entity = world.spawn(("name", 100));
-        assert_eq!(entity.index, 0);
-        assert_eq!(world.entity_count(), 1);
+    /// Mutable handle to a single entity, None if it was despawned.
+    pub fn get_entity_mut(&mut self, entity: Entity) -> Option<EntityMut<'_>> {
+        let location = self.entities.location(entity).ok()?;
+        Some(EntityMut::new(self, entity, location))
     }
 
-    #[test]
-    fn can_remove_entity() {
-        let mut world = World::new();
-        struct Health(usize);
-        let entity = world.spawn((Health(100),));
-        assert_eq!(entity.index, 0);
+    /// Borrow a single component of an entity.
+    /// Returns None if the entity is stale, lacks the component or the component is
+    /// mutably borrowed elsewhere.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// let entity = world.spawn((456, true));
+    /// assert_eq!(*world.get_component::<i32>(entity).unwrap(), 456);
+    /// ```
+    pub fn get_component<T: Component>(&self, entity: Entity) -> Option<ComponentRef<'_, T>> {
+        let location = self.entities.location(entity).ok()?;
+        self.component_ref_at(location).ok()
+    }
+
+    /// Mutably borrow a single component of an entity.
+    /// Returns None if the entity is stale, lacks the component or the component is
+    /// borrowed elsewhere.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// let entity = world.spawn((456, true));
+    /// *world.get_component_mut::<bool>(entity).unwrap() = false;
+    /// ```
+    pub fn get_component_mut<T: Component>(&self, entity: Entity) -> Option<ComponentMut<'_, T>> {
+        let location = self.entities.location(entity).ok()?;
+        self.component_mut_at(location)
+    }
+
+    pub(crate) fn component_mut_at<T: Component>(
+        &self,
+        location: EntityLocation,
+    ) -> Option<ComponentMut<'_, T>> {
+        if self
+            .get_archetype(location.archetype_id)
+            .has_tag::<T>(&self.components)
+        {
+            return Some(ComponentMut::new(
+                ColumnMut::tag(1),
+                &TAG_TICKS,
+                self.read_change_tick(),
+                0,
+            ));
+        }
+        let (column, index) = self.component_column::<T>(location)?;
+        Some(ComponentMut::new(
+            column.try_write()?,
+            &column.ticks[index],
+            self.read_change_tick(),
+            index,
+        ))
+    }
+
+    /// Borrow several components of an entity in a single call.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// let entity = world.spawn((456, true));
+    /// let (number, flag) = world.get_many::<(i32, bool)>(entity).unwrap();
+    /// ```
+    pub fn get_many<'world_borrow, T: ComponentRefs<'world_borrow>>(
+        &'world_borrow self,
+        entity: Entity,
+    ) -> Result<T::Refs, EcsError> {
+        let location = self
+            .entities
+            .location(entity)
+            .map_err(EcsError::EntityErr)?;
+        T::get_refs(self, location)
+    }
+
+    pub(crate) fn component_ref_at<T: Component>(
+        &self,
+        location: EntityLocation,
+    ) -> Result<ComponentRef<'_, T>, EcsError> {
+        if self
+            .get_archetype(location.archetype_id)
+            .has_tag::<T>(&self.components)
+        {
+            return Ok(ComponentRef::new(ColumnRef::tag(1), 0));
+        }
+        let (column, index) = self
+            .component_column::<T>(location)
+            .ok_or(EcsError::ArchetypeErr(ArchetypeError::ComponentMissing))?;
+        let column = column
+            .try_read()
+            .ok_or(EcsError::QueryErr(FetchError::AlreadyBorrowed))?;
+        Ok(ComponentRef::new(column, index))
+    }
+
+    pub fn has_component<T: Component>(&self, entity: Entity) -> bool {
+        self.entities
+            .location(entity)
+            .is_ok_and(|location| self.has_component_at::<T>(location))
+    }
+
+    pub(crate) fn has_component_at<T: Component>(&self, location: EntityLocation) -> bool {
+        self.get_archetype(location.archetype_id)
+            .has_component::<T>(&self.components)
+            || self.component_column::<T>(location).is_some()
+    }
+
+    /// Borrow the components of every entity matching 'T'.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// world.spawn((456, true));
+    /// for (number, flag) in world.query::<(&i32, &mut bool)>().unwrap().iter() {
+    ///     *flag = *number > 100;
+    /// }
+    /// ```
+    pub fn query<'world_borrow, T: QueryParameters>(
+        &'world_borrow self,
+    ) -> Result<Query<'world_borrow, T>, EcsError> {
+        query::<T, ()>(self, self.world_ticks()).map_err(EcsError::QueryErr)
+    }
+
+    /// Like 'query', but only archetypes passing the filter 'F' are matched.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// let mut world = World::new();
+    /// world.spawn((456, true));
+    /// world.spawn((789,));
+    /// let count = world
+    ///     .query_filtered::<&i32, Without<bool>>()
+    ///     .unwrap()
+    ///     .iter()
+    ///     .count();
+    /// ```
+    pub fn query_filtered<'world_borrow, T: QueryParameters, F: QueryFilter>(
+        &'world_borrow self,
+    ) -> Result<Query<'world_borrow, T, F>, EcsError> {
+        query::<T, F>(self, self.world_ticks()).map_err(EcsError::QueryErr)
+    }
+
+    /// Insert a resource, replacing and returning the previous one of the same type.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// struct DeltaTime(f32);
+    /// let mut world = World::new();
+    /// world.insert_resource(DeltaTime(0.016));
+    /// ```
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) -> Option<R> {
+        self.resources.insert(resource)
+    }
+
+    /// Remove a resource, returning it if it existed.
+    pub fn remove_resource<R: Resource>(&mut self) -> Option<R> {
+        self.resources.remove::<R>()
+    }
+
+    pub fn has_resource<R: Resource>(&self) -> bool {
+        self.resources.contains::<R>()
+    }
+
+    /// Borrow a resource.
+    /// Fails if it doesn't exist or is mutably borrowed elsewhere.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// struct DeltaTime(f32);
+    /// let mut world = World::new();
+    /// world.insert_resource(DeltaTime(0.016));
+    /// let dt = world.resource::<DeltaTime>().unwrap();
+    /// ```
+    pub fn resource<R: Resource>(&self) -> Result<Res<'_, R>, EcsError> {
+        self.resources.get::<R>().map_err(EcsError::ResourceErr)
+    }
+
+    /// Mutably borrow a resource.
+    /// Fails if it doesn't exist or is borrowed elsewhere.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// struct DeltaTime(f32);
+    /// let mut world = World::new();
+    /// world.insert_resource(DeltaTime(0.016));
+    /// world.resource_mut::<DeltaTime>().unwrap().0 = 0.032;
+    /// ```
+    pub fn resource_mut<R: Resource>(&self) -> Result<ResMut<'_, R>, EcsError> {
+        self.resources.get_mut::<R>().map_err(EcsError::ResourceErr)
+    }
+
+    /// Insert a resource that isn't 'Send' or 'Sync', like an SDL texture, replacing and
+    /// returning the previous one of the same type.
+    /// It can only be used from the current thread, systems taking it as 'NonSend' or
+    /// 'NonSendMut' run on the thread calling 'update'.
+    /// # Panics
+    /// If the previous one was inserted on another thread.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// use std::rc::Rc;
+    /// let mut world = World::new();
+    /// world.insert_non_send_resource(Rc::new(String::from("textures")));
+    /// ```
+    pub fn insert_non_send_resource<R: 'static>(&mut self, resource: R) -> Option<R> {
+        self.non_send_resources.insert(resource)
+    }
+
+    /// Remove a non-send resource, returning it if it existed.
+    /// # Panics
+    /// If it was inserted on another thread.
+    pub fn remove_non_send_resource<R: 'static>(&mut self) -> Option<R> {
+        self.non_send_resources.remove::<R>()
+    }
+
+    /// Borrow a non-send resource.
+    /// Fails if it doesn't exist or is mutably borrowed elsewhere.
+    /// # Panics
+    /// If it was inserted on another thread.
+    pub fn non_send_resource<R: 'static>(&self) -> Result<NonSend<'_, R>, EcsError> {
+        self.non_send_resources
+            .get::<R>()
+            .map_err(EcsError::ResourceErr)
+    }
+
+    /// Mutably borrow a non-send resource.
+    /// Fails if it doesn't exist or is borrowed elsewhere.
+    /// # Panics
+    /// If it was inserted on another thread.
+    pub fn non_send_resource_mut<R: 'static>(&self) -> Result<NonSendMut<'_, R>, EcsError> {
+        self.non_send_resources
+            .get_mut::<R>()
+            .map_err(EcsError::ResourceErr)
+    }
+
+    /// Register a system, it runs on every 'update' after the systems added before it.
+    /// Both types implementing 'System' and functions whose arguments are 'SystemParam's
+    /// can be registered.
+    /// # Example
+    /// ```
+    /// # use ecs::*;
+    /// struct Gravity;
+    /// impl System for Gravity {
+    ///     fn run(&mut self, world: &World) -> Result<(), EcsError> {
+    ///         Ok(())
+    ///     }
+    /// }
+    /// let mut world = World::new();
+    /// fn movement(query: Query<&mut f32>, dt: Res<DeltaTime>) {}
+    /// let mut world = World::new();
+    /// world.add_system(Gravity);
+    /// world.add_system(movement);
+    /// world.update(0.016).unwrap();
+    /// ```
+    pub fn add_system<Params>(&mut self, system: impl IntoSystem<Params>) {
+        self.schedule.add(Box::new(system.into_system()));
+    }
+
+    /// Unregister the first system of type 'S', returning it.
+    pub fn remove_system<S: System>(&mut self) -> Option<S> {
+        self.schedule.remove::<S>()
+    }
+
+    /// Unregister the first system with this label, returning it.
+    pub fn remove_system_by_label(&mut self, label: &str) -> Option<Box<dyn System>> {
+        self.schedule.remove_by_label(label)
+    }
+
+    pub fn has_system<S: System>(&self) -> bool {
+        self.schedule.get::<S>().is_some()
+    }
+
+    pub fn has_system_by_label(&self, label: &str) -> bool {
+        self.schedule.get_by_label(label).is_some()
+    }
+
+    pub fn get_system<S: System>(&self) -> Option<&S> {
+        self.schedule.get::<S>()
+    }
+
+    pub fn get_system_mut<S: System>(&mut self) -> Option<&mut S> {
+        self.schedule.get_mut::<S>()
+    }
+
+    pub fn get_system_by_label(&self, label: &str) -> Option<&dyn System> {
+        self.schedule.get_by_label(label)
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn can_create_entity() {
+        let mut world = World::new();
+        let entity = world.spawn(("name", 100));
+        assert_eq!(entity.index, 0);
+        assert_eq!(world.entity_count(), 1);
+    }
+
+    #[test]
+    fn can_remove_entity() {
+        let mut world = World::new();
+        struct Health(usize);
+        let entity = world.spawn((Health(100),));
+        assert_eq!(entity.index, 0);
         assert_eq!(world.entity_count(), 1);
 
-        world.remove(entity);
+        world.remove(entity).unwrap();
         assert_eq!(world.entity_count(), 0);
+        assert!(world.remove(entity).is_err());
     }
 
     #[test]
@@ -399,7 +1434,7 @@ mod tests {
     #[test]
     fn can_get_entity_component() {
         let mut world = World::new();
-        let entity = world.spawn(Name("Link"));
+        let entity = world.spawn((Name("Link"),));
         assert_eq!(entity.index, 0);
         assert_eq!(world.entity_count(), 1);
 
@@ -408,94 +1443,1644 @@ mod tests {
         // don't add speed
         struct Speed(usize);
 
-        world.add_component(entity, Health(100));
-        world.add_component(entity, Name("Link"));
+        world.add_component(entity, Health(100)).unwrap();
+        world.add_component(entity, Name("Zelda")).unwrap();
 
         let entity_health = world.get_component::<Health>(entity).unwrap();
         assert_eq!(entity_health.0, 100);
+        assert!(world.get_component_mut::<Health>(entity).is_none());
+        drop(entity_health);
+
+        assert!(world.get_component::<Speed>(entity).is_none());
+
+        world.get_component_mut::<Health>(entity).unwrap().0 = 50;
+        let (health, name) = world.get_many::<(Health, Name)>(entity).unwrap();
+        assert_eq!(health.0, 50);
+        assert_eq!(name.0, "Zelda");
+        drop((health, name));
 
-        let entity_speed = world.get_component::<Speed>(entity);
-        assert!(entity_speed.is_none());
+        assert!(world.get_many::<(Health, Speed)>(entity).is_err());
+        assert!(world.get_component_mut::<Health>(entity).is_some());
+
+        world.remove(entity).unwrap();
+        assert!(world.get_component::<Health>(entity).is_none());
+        assert!(world.get_many::<(Health,)>(entity).is_err());
     }
-}
 
-//
-//     #[test]
-//     fn can_iterate_over_components() {
-//         let mut world = World::new();
-//         let entity = world.spawn();
-//         assert_eq!(entity.index(), 0);
-//         assert_eq!(world.entity_count(), 1);
-//
-//         struct Health(usize);
-//         struct Name(&'static str);
-//         // don't add speed
-//         struct Speed(usize);
-//
-//         world.add_component(entity, Health(100));
-//         world.add_component(entity, Name("Link"));
-//
-//         let entity_health = world.get_component::<Health>(entity).unwrap();
-//         assert_eq!(entity_health.0, 100);
-//
-//         for health in world.query::<Health>().filter_map(|h| h.as_ref()) {
-//             assert_eq!(health.0, 100);
-//         }
-//     }
-//
-//     #[test]
-//     fn can_iterate_mutably_over_components() {
-//         let mut world = World::new();
-//         let entity = world.spawn();
-//         assert_eq!(entity.index(), 0);
-//         assert_eq!(world.entity_count(), 1);
-//
-//         struct Health(usize);
-//         struct Name(&'static str);
-//         // don't add speed
-//         struct Speed(usize);
-//
-//         world.add_component(entity, Health(100));
-//         world.add_component(entity, Name("Link"));
-//
-//         for health in world.query_mut::<Health>().filter_map(|h| h.as_mut()) {
-//             assert_eq!(health.0, 100);
-//             health.0 = 120;
-//         }
-//
-//         for health in world.query::<Health>().filter_map(|h| h.as_ref()) {
-//             assert_eq!(health.0, 120);
-//         }
-//     }
-//
-//     #[test]
-//     fn can_iterate_mutably_over_multiple_components() {
-//         let mut world = World::new();
-//         let entity = world.spawn();
-//         assert_eq!(entity.index(), 0);
-//         assert_eq!(world.entity_count(), 1);
-//
-//         struct Health(usize);
-//         struct Name(&'static str);
-//
-//         world.add_component(entity, Health(100));
-//         world.add_component(entity, Name("Link"));
-//
-//         let pools = world.query_mut::<(Health, Name)>();
-//
-//         for (health, name) in world
-//             .query_mut::<(Health, Name)>()
-//             .filter_map(|(h, n)| Some((h.as_mut()?, n.as_mut()?)))
-//         {
-//             assert_eq!(health.0, 100);
-//             assert_eq!(name.0, "Link");
-//             health.0 = 120;
-//             name.0 = "Zelda";
-//         }
-//         //
-//         // for (health, name) in zip.map(|t| (t.0.unwrap(), t.1.unwrap())) {
-//         //     assert_eq!(health.0, 120);
-//         //     assert_eq!(name.0, "Zelda");
-//         // }
-//     }
-// }
+    #[test]
+    fn can_iterate_over_components() {
+        let mut world = World::new();
+        struct Health(usize);
+        struct Name(&'static str);
+
+        let entity = world.spawn((Health(100),));
+        world.add_component(entity, Name("Link")).unwrap();
+        world.spawn((Health(100),));
+
+        let mut count = 0;
+        for health in world.query::<&Health>().unwrap().iter() {
+            assert_eq!(health.0, 100);
+            count += 1;
+        }
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn can_iterate_mutably_over_components() {
+        let mut world = World::new();
+        struct Health(usize);
+        struct Name(&'static str);
+
+        world.spawn((Health(100),));
+        world.spawn((Health(100), Name("Link")));
+        world.spawn((Name("Zelda"),));
+
+        for mut health in world.query::<&mut Health>().unwrap().iter() {
+            assert_eq!(health.0, 100);
+            health.0 = 120;
+        }
+
+        let mut query = world.query::<&Health>().unwrap();
+        assert_eq!(query.iter().count(), 2);
+        for health in &mut query {
+            assert_eq!(health.0, 120);
+        }
+    }
+
+    #[test]
+    fn query_fails_on_conflicting_borrows() {
+        let mut world = World::new();
+        struct Health(usize);
+
+        world.spawn((Health(100),));
+
+        let _reading = world.query::<&Health>().unwrap();
+        assert!(world.query::<&mut Health>().is_err());
+    }
+
+    #[test]
+    fn can_iterate_mutably_over_multiple_components() {
+        let mut world = World::new();
+        struct Health(usize);
+        struct Name(&'static str);
+        struct Speed(usize);
+
+        world.spawn((Health(100), Name("Link")));
+        world.spawn((Speed(10), Health(100), Name("Link")));
+        world.spawn((Health(100),));
+
+        let mut count = 0;
+        for (mut health, mut name) in world.query::<(&mut Health, &mut Name)>().unwrap().iter() {
+            assert_eq!(health.0, 100);
+            assert_eq!(name.0, "Link");
+            health.0 = 120;
+            name.0 = "Zelda";
+            count += 1;
+        }
+        assert_eq!(count, 2);
+
+        for (name, health) in world.query::<(&Name, &Health)>().unwrap().iter() {
+            assert_eq!(health.0, 120);
+            assert_eq!(name.0, "Zelda");
+        }
+    }
+
+    #[test]
+    fn query_fails_on_aliasing_parameters() {
+        let mut world = World::new();
+        struct Health(usize);
+
+        world.spawn((Health(100),));
+
+        assert!(world.query::<(&Health, &mut Health)>().is_err());
+    }
+
+    #[test]
+    fn can_iterate_over_optional_components() {
+        let mut world = World::new();
+        struct Sprite(&'static str);
+        struct Animation(usize);
+
+        world.spawn((Sprite("tank"),));
+        world.spawn((Sprite("chopper"), Animation(0)));
+
+        for (sprite, animation) in world
+            .query::<(&Sprite, Option<&mut Animation>)>()
+            .unwrap()
+            .iter()
+        {
+            match sprite.0 {
+                "tank" => assert!(animation.is_none()),
+                _ => animation.unwrap().0 += 1,
+            }
+        }
+
+        let animations = world
+            .query::<Option<&Animation>>()
+            .unwrap()
+            .iter()
+            .map(|animation| animation.map(|a| a.0))
+            .collect::<Vec<_>>();
+        assert_eq!(animations.len(), 2);
+        assert!(animations.contains(&None));
+        assert!(animations.contains(&Some(1)));
+    }
+
+    #[test]
+    fn can_filter_queries() {
+        use crate::ecs::queries::query_filters::{Or, With, Without};
+
+        let mut world = World::new();
+        struct Transform(usize);
+        struct Player;
+        struct Enemy;
+        struct Dead;
+
+        world.spawn((Transform(0), Player));
+        world.spawn((Transform(1), Player, Dead));
+        world.spawn((Transform(2), Enemy));
+        world.spawn((Transform(3),));
+
+        let positions = |world: &World| {
+            let mut positions = world
+                .query_filtered::<&Transform, (With<Player>, Without<Dead>)>()
+                .unwrap()
+                .iter()
+                .map(|t| t.0)
+                .collect::<Vec<_>>();
+            positions.sort_unstable();
+            positions
+        };
+        assert_eq!(positions(&world), vec![0]);
+
+        let mut positions = world
+            .query_filtered::<&Transform, Or<(With<Player>, With<Enemy>)>>()
+            .unwrap()
+            .iter()
+            .map(|t| t.0)
+            .collect::<Vec<_>>();
+        positions.sort_unstable();
+        assert_eq!(positions, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn can_query_entities() {
+        let mut world = World::new();
+        struct Health(usize);
+
+        let first = world.spawn((Health(0),));
+        let second = world.spawn((Health(100), "enemy"));
+        let third = world.spawn((Health(0), "enemy"));
+
+        let mut dead = world
+            .query::<(Entity, &Health)>()
+            .unwrap()
+            .iter()
+            .filter(|(_, health)| health.0 == 0)
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        dead.sort_unstable();
+        assert_eq!(dead, vec![first, third]);
+
+        let mut entities = world.query::<Entity>().unwrap().iter().collect::<Vec<_>>();
+        entities.sort_unstable();
+        assert_eq!(entities, vec![first, second, third]);
+    }
+
+    #[test]
+    fn removed_entities_are_despawned() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+        struct Health(usize);
+        impl Drop for Health {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let mut world = World::new();
+        let first = world.spawn((Health(0),));
+        let second = world.spawn((Health(1),));
+        let third = world.spawn((Health(2),));
+
+        world.remove(first).unwrap();
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+        assert!(world.remove(first).is_err());
+
+        // The last entity was moved into the removed one's row
+        assert_eq!(world.get_component::<Health>(third).unwrap().0, 2);
+        assert_eq!(world.get_component::<Health>(second).unwrap().0, 1);
+
+        let mut healths = world
+            .query::<&Health>()
+            .unwrap()
+            .iter()
+            .map(|health| health.0)
+            .collect::<Vec<_>>();
+        healths.sort_unstable();
+        assert_eq!(healths, vec![1, 2]);
+
+        // The freed index is reused, the stale handle must not reach the new entity
+        let fourth = world.spawn((Health(3),));
+        assert_eq!(fourth.index, first.index);
+        assert!(world.get_component::<Health>(first).is_none());
+        assert!(world.remove(first).is_err());
+        assert_eq!(world.get_component::<Health>(fourth).unwrap().0, 3);
+    }
+
+    #[test]
+    fn can_use_resources() {
+        let mut world = World::new();
+        struct DeltaTime(f32);
+        struct Map(&'static str);
+
+        assert!(world.resource::<DeltaTime>().is_err());
+        assert!(world.insert_resource(DeltaTime(0.016)).is_none());
+        world.insert_resource(Map("jungle"));
+        assert!(world.has_resource::<Map>());
+
+        {
+            let dt = world.resource::<DeltaTime>().unwrap();
+            let other_dt = world.resource::<DeltaTime>().unwrap();
+            assert_eq!(dt.0, other_dt.0);
+            // Shared borrows block exclusive ones
+            assert!(world.resource_mut::<DeltaTime>().is_err());
+            // Resources don't block each other
+            world.resource_mut::<Map>().unwrap().0 = "desert";
+        }
+
+        world.resource_mut::<DeltaTime>().unwrap().0 = 0.032;
+        assert_eq!(world.resource::<DeltaTime>().unwrap().0, 0.032);
+
+        let previous = world.insert_resource(DeltaTime(0.5)).unwrap();
+        assert_eq!(previous.0, 0.032);
+
+        assert_eq!(world.remove_resource::<Map>().unwrap().0, "desert");
+        assert!(!world.has_resource::<Map>());
+        assert!(world.remove_resource::<Map>().is_none());
+    }
+
+    #[test]
+    fn can_run_systems() {
+        let mut world = World::new();
+        struct Position(f32);
+        struct Velocity(f32);
+        #[derive(Default)]
+        struct Log(Vec<&'static str>);
+
+        struct Movement;
+        impl System for Movement {
+            fn run(&mut self, world: &World) -> Result<(), EcsError> {
+                let dt = world.resource::<DeltaTime>()?;
+                for (mut position, velocity) in world.query::<(&mut Position, &Velocity)>()?.iter()
+                {
+                    position.0 += velocity.0 * dt.0;
+                }
+                world.resource_mut::<Log>()?.0.push("movement");
+                Ok(())
+            }
+        }
+
+        struct Render {
+            frames: usize,
+        }
+        impl System for Render {
+            fn label(&self) -> &str {
+                "render"
+            }
+
+            fn run(&mut self, world: &World) -> Result<(), EcsError> {
+                self.frames += 1;
+                world.resource_mut::<Log>()?.0.push("render");
+                Ok(())
+            }
+        }
+
+        world.insert_resource(Log::default());
+        let entity = world.spawn((Position(0.0), Velocity(2.0)));
+        world.add_system(Movement);
+        world.add_system(Render { frames: 0 });
+        assert!(world.has_system::<Movement>());
+        assert!(world.has_system_by_label("render"));
+
+        world.update(0.5).unwrap();
+        world.update(0.5).unwrap();
+        assert_eq!(world.get_component::<Position>(entity).unwrap().0, 2.0);
+        assert_eq!(world.get_system::<Render>().unwrap().frames, 2);
+        assert_eq!(
+            world.resource::<Log>().unwrap().0,
+            vec!["movement", "render", "movement", "render"]
+        );
+
+        assert!(world.remove_system_by_label("render").is_some());
+        assert!(world.get_system_by_label("render").is_none());
+        assert!(world.remove_system::<Movement>().is_some());
+        assert!(!world.has_system::<Movement>());
+
+        world.update(0.5).unwrap();
+        assert_eq!(world.resource::<Log>().unwrap().0.len(), 4);
+    }
+
+    #[test]
+    fn system_errors_stop_the_update() {
+        let mut world = World::new();
+        struct Missing;
+
+        struct NeedsResource;
+        impl System for NeedsResource {
+            fn run(&mut self, world: &World) -> Result<(), EcsError> {
+                world.resource::<Missing>()?;
+                Ok(())
+            }
+        }
+
+        world.add_system(NeedsResource);
+        assert!(world.update(0.016).is_err());
+        world.insert_resource(Missing);
+        assert!(world.update(0.016).is_ok());
+    }
+
+    #[test]
+    fn can_run_function_systems() {
+        use crate::ecs::queries::query_filters::With;
+        use crate::ecs::resources::{Res, ResMut};
+
+        let mut world = World::new();
+        struct Transform(f32);
+        struct RigidBody(f32);
+        struct Player;
+        #[derive(Default)]
+        struct Frames(usize);
+
+        fn movement(mut query: Query<(&mut Transform, &RigidBody)>, dt: Res<DeltaTime>) {
+            for (mut transform, rigid_body) in query.iter() {
+                transform.0 += rigid_body.0 * dt.0;
+            }
+        }
+
+        fn count_frames(mut frames: ResMut<Frames>) {
+            frames.0 += 1;
+        }
+
+        fn reset_players(mut query: Query<&mut Transform, With<Player>>) {
+            for mut transform in query.iter() {
+                transform.0 = 0.0;
+            }
+        }
+
+        world.insert_resource(Frames::default());
+        let player = world.spawn((Transform(0.0), RigidBody(1.0), Player));
+        let tank = world.spawn((Transform(0.0), RigidBody(2.0)));
+        world.add_system(movement);
+        world.add_system(count_frames);
+        world.add_system(|| {});
+
+        world.update(0.5).unwrap();
+        world.update(0.5).unwrap();
+        assert_eq!(world.get_component::<Transform>(player).unwrap().0, 1.0);
+        assert_eq!(world.get_component::<Transform>(tank).unwrap().0, 2.0);
+        assert_eq!(world.resource::<Frames>().unwrap().0, 2);
+
+        world.add_system(reset_players);
+        world.update(0.5).unwrap();
+        assert_eq!(world.get_component::<Transform>(player).unwrap().0, 0.0);
+        assert_eq!(world.get_component::<Transform>(tank).unwrap().0, 3.0);
+
+        let label = std::any::type_name_of_val(&count_frames);
+        assert!(world.has_system_by_label(label));
+        assert!(world.remove_system_by_label(label).is_some());
+        world.update(0.5).unwrap();
+        assert_eq!(world.resource::<Frames>().unwrap().0, 3);
+
+        // Missing resources surface as errors instead of panics
+        world.remove_resource::<Frames>();
+        world.add_system(count_frames);
+        assert!(world.update(0.5).is_err());
+    }
+
+    #[test]
+    fn compatible_systems_share_a_batch() {
+        use crate::ecs::resources::{Res, ResMut};
+
+        let mut world = World::new();
+        struct Transform(f32);
+        struct RigidBody(f32);
+        struct Health(usize);
+        struct Score(usize);
+
+        fn physics(_query: Query<(&mut Transform, &RigidBody)>) {}
+        fn ai(_query: Query<(&Transform, &mut Health)>) {}
+        fn render(_query: Query<(&Transform, Option<&RigidBody>)>, _score: Res<Score>) {}
+        fn scoring(_query: Query<&Health>, _score: ResMut<Score>) {}
+        fn hud(_score: Res<Score>) {}
+
+        world.add_system(physics);
+        world.add_system(ai);
+        world.add_system(render);
+        world.add_system(scoring);
+        world.add_system(hud);
+        // Touches nothing, so it fits anywhere
+        world.add_system(|| {});
+        // Raw systems without declared access run on their own
+        struct Opaque;
+        impl System for Opaque {
+            fn run(&mut self, _world: &World) -> Result<(), EcsError> {
+                Ok(())
+            }
+        }
+        world.add_system(Opaque);
+
+        // ai reads Transform, which physics writes
+        // scoring reads Health, which ai writes
+        // hud reads Score, which scoring writes
+        assert_eq!(world.schedule.batches(), vec![0..1, 1..3, 3..4, 4..6, 6..7]);
+    }
+
+    #[test]
+    fn compatible_systems_run_in_parallel() {
+        let mut world = World::new();
+        struct Transform(f32);
+        struct Health(usize);
+        struct Velocity(f32);
+
+        fn physics(mut query: Query<(&mut Transform, &Velocity)>) {
+            for (mut transform, velocity) in query.iter() {
+                transform.0 += velocity.0;
+            }
+        }
+        fn ai(mut query: Query<(&mut Health, &Velocity)>) {
+            for (mut health, _) in query.iter() {
+                health.0 -= 1;
+            }
+        }
+        fn heal(mut query: Query<&mut Health>) {
+            for mut health in query.iter() {
+                health.0 += 5;
+            }
+        }
+
+        let entity = world.spawn((Transform(0.0), Health(10), Velocity(1.0)));
+        world.add_system(physics);
+        world.add_system(ai);
+        world.add_system(heal);
+        // Physics and AI only share reads, so they're handed to the workers together
+        assert_eq!(world.schedule.batches(), vec![0..2, 2..3]);
+        world.update(0.016).unwrap();
+
+        assert_eq!(world.get_component::<Transform>(entity).unwrap().0, 1.0);
+        assert_eq!(world.get_component::<Health>(entity).unwrap().0, 14);
+    }
+
+    #[test]
+    fn parallel_batches_reuse_worker_threads() {
+        use crate::ecs::resources::Res;
+        use std::{collections::HashSet, sync::Mutex, thread};
+
+        let mut world = World::new();
+        #[derive(Default)]
+        struct Threads(Mutex<HashSet<thread::ThreadId>>);
+
+        fn record(threads: Res<Threads>) {
+            threads.0.lock().unwrap().insert(thread::current().id());
+        }
+        fn record_too(threads: Res<Threads>) {
+            threads.0.lock().unwrap().insert(thread::current().id());
+        }
+
+        world.insert_resource(Threads::default());
+        world.add_system(record);
+        world.add_system(record_too);
+        for _ in 0..50 {
+            world.update(0.016).unwrap();
+        }
+        let workers = thread::available_parallelism().unwrap().get();
+        assert!(world.resource::<Threads>().unwrap().0.lock().unwrap().len() <= workers);
+    }
+
+    #[test]
+    fn tick_filters_conflict_with_writers() {
+        use crate::ecs::queries::query_filters::{Changed, Or, With};
+
+        let mut world = World::new();
+        struct Transform(f32);
+        struct Health(usize);
+
+        fn physics(mut query: Query<&mut Transform>) {
+            for mut transform in query.iter() {
+                transform.0 += 1.0;
+            }
+        }
+        type Moved = Or<(Changed<Transform>, Changed<Health>)>;
+
+        fn rebuild_grid(mut query: Query<Entity, Moved>) {
+            query.iter().count();
+        }
+        fn count_living(mut query: Query<Entity, With<Transform>>) {
+            query.iter().count();
+        }
+
+        world.spawn((Transform(0.0), Health(10)));
+        world.add_system(physics);
+        world.add_system(rebuild_grid);
+        world.add_system(count_living);
+        // Changed reads the ticks physics stamps, With only looks at archetypes
+        assert_eq!(world.schedule.batches(), vec![0..1, 1..3]);
+        world.update(0.016).unwrap();
+    }
+
+    #[test]
+    fn can_defer_structural_changes() {
+        use crate::ecs::commands::Commands;
+
+        let mut world = World::new();
+        struct Health(usize);
+        struct Projectile(usize);
+        struct Stunned;
+
+        fn damage(mut query: Query<(Entity, &Health)>, mut commands: Commands) {
+            for (entity, health) in query.iter() {
+                if health.0 == 0 {
+                    commands.remove(entity);
+                } else {
+                    let projectile = commands.spawn((Projectile(health.0),));
+                    commands.add_component(projectile, Stunned);
+                    commands.add_component(entity, Stunned);
+                }
+            }
+        }
+
+        let dead = world.spawn((Health(0),));
+        let alive = world.spawn((Health(10),));
+        world.add_system(damage);
+        world.update(0.016).unwrap();
+
+        assert!(world.get_component::<Health>(dead).is_none());
+        assert!(world.has_component::<Stunned>(alive));
+        let projectiles = world
+            .query::<(Entity, &Projectile)>()
+            .unwrap()
+            .iter()
+            .map(|(entity, projectile)| (entity, projectile.0))
+            .collect::<Vec<_>>();
+        assert_eq!(projectiles.len(), 1);
+        assert!(world.has_component::<Stunned>(projectiles[0].0));
+        assert_eq!(projectiles[0].1, 10);
+        assert_eq!(world.entity_count(), 2);
+    }
+
+    #[test]
+    fn reserved_entities_are_not_reused() {
+        let mut world = World::new();
+        struct Health(usize);
+
+        let reserved = {
+            let mut commands = world.commands();
+            let first = commands.spawn((Health(1),));
+            let second = commands.spawn((Health(2),));
+            assert_ne!(first, second);
+            (first, second)
+        };
+        assert!(world.get_component::<Health>(reserved.0).is_none());
+
+        // Spawning directly must not hand out a reserved index
+        let direct = world.spawn((Health(3),));
+        assert_ne!(direct.index, reserved.0.index);
+        assert_ne!(direct.index, reserved.1.index);
+
+        world.apply_commands();
+        assert_eq!(world.get_component::<Health>(reserved.0).unwrap().0, 1);
+        assert_eq!(world.get_component::<Health>(reserved.1).unwrap().0, 2);
+        assert_eq!(world.get_component::<Health>(direct).unwrap().0, 3);
+        assert_eq!(world.entity_count(), 3);
+
+        // Commands on stale entities fail without stopping the others
+        world.remove(direct).unwrap();
+        let mut commands = world.commands();
+        commands.remove(direct);
+        commands.remove_component::<Health>(reserved.0);
+        drop(commands);
+        world.apply_commands();
+        assert!(!world.has_component::<Health>(reserved.0));
+
+        // Nor do they reach the entity that got the stale one's index
+        let reused = world.spawn((Health(4),));
+        assert_eq!(reused.index, direct.index);
+        let mut commands = world.commands();
+        commands.add_component(direct, true);
+        commands.add_component(direct, Health(5));
+        commands.remove_component::<Health>(direct);
+        drop(commands);
+        world.apply_commands();
+        assert!(!world.has_component::<bool>(reused));
+        assert_eq!(world.get_component::<Health>(reused).unwrap().0, 4);
+    }
+
+    #[test]
+    fn poisoned_resources_can_be_replaced() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut world = World::new();
+        struct Score(u32);
+
+        world.insert_resource(Score(1));
+        let panicked = catch_unwind(AssertUnwindSafe(|| {
+            let _score = world.resource_mut::<Score>().unwrap();
+            panic!("system failed while writing the score");
+        }));
+        assert!(panicked.is_err());
+        assert!(world.resource::<Score>().is_err());
+
+        assert_eq!(world.insert_resource(Score(2)).unwrap().0, 1);
+        assert_eq!(world.remove_resource::<Score>().unwrap().0, 2);
+    }
+
+    #[test]
+    fn unclaimed_reservations_are_freed() {
+        let mut world = World::new();
+        struct Health(usize);
+
+        // Reserved, but no command ever spawns it
+        let lost = world.entities().reserve();
+        world.spawn((Health(1),));
+        world.apply_commands();
+
+        let reused = world.spawn((Health(2),));
+        assert_eq!(reused.index, lost.index);
+        assert_ne!(reused, lost);
+        assert!(!world.has_component::<Health>(lost));
+        world.commands().add_component(lost, Health(3));
+        world.apply_commands();
+        assert_eq!(world.get_component::<Health>(reused).unwrap().0, 2);
+        assert_eq!(world.entity_count(), 2);
+    }
+
+    #[test]
+    fn systems_communicate_through_events() {
+        use crate::ecs::events::{EventCursor, EventReader, EventWriter};
+        use crate::ecs::resources::ResMut;
+
+        let mut world = World::new();
+        struct CollisionEvent(usize, usize);
+        #[derive(Default)]
+        struct Seen(Vec<(&'static str, usize)>);
+
+        // Runs before the writer, so it only sees events on the next update
+        fn early_damage(mut events: EventReader<CollisionEvent>, mut seen: ResMut<Seen>) {
+            for event in events.read() {
+                seen.0.push(("early", event.0 + event.1));
+            }
+        }
+        fn collision(mut events: EventWriter<CollisionEvent>, mut frame: ResMut<usize>) {
+            *frame += 1;
+            if *frame <= 2 {
+                events.send(CollisionEvent(*frame, 10));
+            }
+        }
+        fn late_damage(mut events: EventReader<CollisionEvent>, mut seen: ResMut<Seen>) {
+            for event in events.read() {
+                seen.0.push(("late", event.0 + event.1));
+            }
+        }
+
+        world.add_event::<CollisionEvent>();
+        world.insert_resource(Seen::default());
+        world.insert_resource(0usize);
+        world.add_system(early_damage);
+        world.add_system(collision);
+        world.add_system(late_damage);
+
+        for _ in 0..4 {
+            world.update(0.016).unwrap();
+        }
+        assert_eq!(
+            world.resource::<Seen>().unwrap().0,
+            vec![("late", 11), ("early", 11), ("late", 12), ("early", 12)]
+        );
+        // Events are gone two updates after they were sent
+        let events = world.resource::<Events<CollisionEvent>>().unwrap();
+        assert_eq!(EventCursor::default().read(&events).count(), 0);
+    }
+
+    #[test]
+    fn events_live_for_two_updates() {
+        use crate::ecs::events::EventCursor;
+
+        let mut world = World::new();
+        struct FireProjectile(usize);
+
+        world.add_event::<FireProjectile>();
+        world.send_event(FireProjectile(1)).unwrap();
+
+        let mut slow_reader = EventCursor::<FireProjectile>::default();
+        let mut fast_reader = EventCursor::<FireProjectile>::default();
+        let read = |cursor: &mut EventCursor<FireProjectile>, world: &World| {
+            let events = world.resource::<Events<FireProjectile>>().unwrap();
+            cursor
+                .read(&events)
+                .map(|event| event.0)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(read(&mut fast_reader, &world), vec![1]);
+        world.update(0.016).unwrap();
+        world.send_event(FireProjectile(2)).unwrap();
+        assert_eq!(read(&mut fast_reader, &world), vec![2]);
+        assert_eq!(read(&mut slow_reader, &world), vec![1, 2]);
+        assert!(read(&mut slow_reader, &world).is_empty());
+
+        world.update(0.016).unwrap();
+        world.update(0.016).unwrap();
+        let mut late_reader = EventCursor::<FireProjectile>::default();
+        assert!(read(&mut late_reader, &world).is_empty());
+    }
+
+    #[test]
+    fn changed_filter_only_matches_written_components() {
+        use crate::ecs::queries::query_filters::Changed;
+        use crate::ecs::resources::ResMut;
+
+        let mut world = World::new();
+        struct Transform(f32);
+        struct Velocity(f32);
+        #[derive(Default)]
+        struct Rebuilt(Vec<usize>);
+
+        fn movement(mut query: Query<(&mut Transform, &Velocity)>) {
+            for (mut transform, velocity) in query.iter() {
+                if velocity.0 != 0.0 {
+                    transform.0 += velocity.0;
+                }
+            }
+        }
+
+        fn rebuild_grid(
+            mut query: Query<&Transform, Changed<Transform>>,
+            mut rebuilt: ResMut<Rebuilt>,
+        ) {
+            let count = query.iter().count();
+            rebuilt.0.push(count);
+        }
+
+        world.insert_resource(Rebuilt::default());
+        let tank = world.spawn((Transform(0.0), Velocity(1.0)));
+        let tree = world.spawn((Transform(0.0), Velocity(0.0)));
+        let rock = world.spawn((Transform(0.0),));
+        world.add_system(movement);
+        world.add_system(rebuild_grid);
+
+        // Everything is new on the first run, then only the tank keeps moving
+        world.update(0.016).unwrap();
+        world.update(0.016).unwrap();
+        assert_eq!(world.resource::<Rebuilt>().unwrap().0, vec![3, 1]);
+
+        world.remove_system_by_label(std::any::type_name_of_val(&movement));
+        world.update(0.016).unwrap();
+        world.get_component_mut::<Transform>(rock).unwrap().0 = 5.0;
+        world.update(0.016).unwrap();
+        world.add_component(tree, Transform(1.0)).unwrap();
+        world.update(0.016).unwrap();
+        assert_eq!(world.resource::<Rebuilt>().unwrap().0, vec![3, 1, 0, 1, 1]);
+        assert_eq!(world.get_component::<Transform>(tank).unwrap().0, 2.0);
+    }
+
+    #[test]
+    fn can_detect_added_and_changed_components() {
+        use crate::ecs::change_detection::Ref;
+        use crate::ecs::queries::query_filters::{Added, Or};
+
+        let mut world = World::new();
+        struct Health(u32);
+        struct Shield(u32);
+
+        let tank = world.spawn((Health(100),));
+        for health in world.query::<Ref<Health>>().unwrap().iter() {
+            assert!(health.is_added());
+            assert!(health.is_changed());
+        }
+
+        world.update(0.016).unwrap();
+        for health in world.query::<Ref<Health>>().unwrap().iter() {
+            assert!(!health.is_changed());
+        }
+
+        // Mutable access alone doesn't count as a change
+        for health in world.query::<&mut Health>().unwrap().iter() {
+            assert_eq!(health.0, 100);
+        }
+        assert_eq!(
+            world
+                .query::<Ref<Health>>()
+                .unwrap()
+                .iter()
+                .filter(|h| h.is_changed())
+                .count(),
+            0
+        );
+
+        world.get_component_mut::<Health>(tank).unwrap().0 = 90;
+        world.add_component(tank, Shield(10)).unwrap();
+        for health in world.query::<Ref<Health>>().unwrap().iter() {
+            assert!(!health.is_added());
+            assert!(health.is_changed());
+            assert_eq!(health.0, 90);
+        }
+        assert_eq!(
+            world
+                .query_filtered::<Entity, Added<Shield>>()
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![tank]
+        );
+
+        world.update(0.016).unwrap();
+        let chopper = world.spawn((Health(50),));
+        let added = world
+            .query_filtered::<Entity, Or<(Added<Health>, Added<Shield>)>>()
+            .unwrap()
+            .iter()
+            .collect::<Vec<_>>();
+        assert_eq!(added, vec![chopper]);
+    }
+
+    #[test]
+    fn systems_see_removed_components_once() {
+        use crate::ecs::removal_detection::RemovedComponents;
+        use crate::ecs::resources::ResMut;
+
+        let mut world = World::new();
+        struct Engine;
+        struct Health(u32);
+        #[derive(Default)]
+        struct Silenced(Vec<Entity>);
+
+        fn stop_engine_sounds(
+            mut removed: RemovedComponents<Engine>,
+            mut silenced: ResMut<Silenced>,
+        ) {
+            silenced.0.extend(removed.read());
+        }
+
+        world.insert_resource(Silenced::default());
+        let chopper = world.spawn((Engine, Health(50)));
+        let tank = world.spawn((Engine, Health(100)));
+        let soldier = world.spawn((Health(10),));
+        world.add_system(stop_engine_sounds);
+
+        world.update(0.016).unwrap();
+        assert!(world.resource::<Silenced>().unwrap().0.is_empty());
+
+        world.remove_component::<Engine>(chopper).unwrap();
+        world.remove(tank).unwrap();
+        world.remove(soldier).unwrap();
+        assert_eq!(
+            world.removed::<Engine>().collect::<Vec<_>>(),
+            vec![chopper, tank]
+        );
+        assert_eq!(world.removed::<Health>().count(), 2);
+        world.update(0.016).unwrap();
+        world.update(0.016).unwrap();
+        assert_eq!(world.resource::<Silenced>().unwrap().0, vec![chopper, tank]);
+
+        // Removals are dropped after two updates
+        world.update(0.016).unwrap();
+        assert_eq!(world.removed::<Engine>().count(), 0);
+    }
+
+    #[test]
+    fn component_hooks_keep_indices_in_sync() {
+        let mut world = World::new();
+        struct Name(&'static str);
+        struct Dead;
+        #[derive(Default)]
+        struct Names(HashMap<&'static str, Entity>);
+        #[derive(Default)]
+        struct Added(usize);
+
+        world.insert_resource(Names::default());
+        world.insert_resource(Added::default());
+        world
+            .component_hooks::<Name>()
+            .on_add(|world, _| world.resource_mut::<Added>().unwrap().0 += 1)
+            .on_insert(|world, entity| {
+                let name = world.get_component::<Name>(entity).unwrap().0;
+                world
+                    .resource_mut::<Names>()
+                    .unwrap()
+                    .0
+                    .insert(name, entity);
+            })
+            .on_remove(|world, entity| {
+                let name = world.get_component::<Name>(entity).unwrap().0;
+                world.resource_mut::<Names>().unwrap().0.remove(name);
+            });
+        // Structural changes from hooks are deferred
+        world.component_hooks::<Dead>().on_add(|world, entity| {
+            world.commands().remove(entity);
+        });
+
+        let link = world.spawn((Name("Link"), 100));
+        let zelda = world.commands().spawn((Name("Zelda"),));
+        world.apply_commands();
+        assert_eq!(world.resource::<Names>().unwrap().0["Link"], link);
+        assert_eq!(world.resource::<Names>().unwrap().0["Zelda"], zelda);
+
+        world.add_component(link, Name("Hero")).unwrap();
+        assert_eq!(world.resource::<Names>().unwrap().0["Hero"], link);
+        assert_eq!(world.resource::<Added>().unwrap().0, 2);
+
+        world.remove_component::<Name>(zelda).unwrap();
+        assert!(!world.resource::<Names>().unwrap().0.contains_key("Zelda"));
+        world.add_component(zelda, Name("Zelda")).unwrap();
+        assert_eq!(world.resource::<Added>().unwrap().0, 3);
+
+        world.add_component(zelda, Dead).unwrap();
+        assert!(world.has_component::<Dead>(zelda));
+        world.apply_commands();
+        assert!(!world.has_component::<Dead>(zelda));
+        assert!(!world.resource::<Names>().unwrap().0.contains_key("Zelda"));
+    }
+
+    #[test]
+    fn toggling_a_tag_reuses_cached_transitions() {
+        let mut world = World::new();
+        struct Health(u32);
+        struct Stunned;
+
+        let entities = (0..100_000)
+            .map(|i| world.spawn((Health(i),)))
+            .collect::<Vec<_>>();
+        for _ in 0..2 {
+            for entity in entities.iter() {
+                world.add_component(*entity, Stunned).unwrap();
+            }
+            assert_eq!(
+                world.query::<(&Health, &Stunned)>().unwrap().iter().count(),
+                100_000
+            );
+            for entity in entities.iter() {
+                world.remove_component::<Stunned>(*entity).unwrap();
+            }
+            assert_eq!(world.query::<&Stunned>().unwrap().iter().count(), 0);
+        }
+
+        // Only one transition each way was ever looked up
+        assert_eq!(world.archetypes().len(), 2);
+        let stunned_id = world.components().id::<Stunned>().unwrap();
+        let stunned = world.get_archetype(0).add_edges[&stunned_id];
+        assert_eq!(world.get_archetype(stunned).remove_edges[&stunned_id], 0);
+        let sum = world
+            .query::<&Health>()
+            .unwrap()
+            .iter()
+            .map(|health| health.0 as u64)
+            .sum::<u64>();
+        assert_eq!(sum, (0..100_000u64).sum());
+    }
+
+    #[test]
+    fn archetypes_are_identified_by_their_exact_components() {
+        let mut world = World::new();
+        struct Transform(f32);
+        struct Sprite(&'static str);
+        struct Player;
+
+        let tank = world.spawn((Transform(0.0), Sprite("tank")));
+        let chopper = world.spawn((Sprite("chopper"), Transform(1.0)));
+        let player = world.spawn((Transform(2.0),));
+        world.add_component(player, Sprite("player")).unwrap();
+        let location = |world: &World, entity| world.entities().location(entity).unwrap();
+        assert_eq!(
+            location(&world, tank).archetype_id,
+            location(&world, chopper).archetype_id
+        );
+        assert_eq!(
+            location(&world, tank).archetype_id,
+            location(&world, player).archetype_id
+        );
+
+        world.add_component(player, Player).unwrap();
+        world.remove_component::<Sprite>(player).unwrap();
+        let signatures = world
+            .archetypes()
+            .iter()
+            .map(|archetype| archetype.component_ids().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(signatures.len(), 4);
+        for (index, signature) in signatures.iter().enumerate() {
+            assert_eq!(world.get_bundle_archetype(signature), Some(&index));
+        }
+    }
+
+    #[test]
+    fn columns_move_and_drop_components_exactly_once() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        struct Tracked(Arc<AtomicUsize>);
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        #[repr(align(64))]
+        struct Aligned(u8);
+        struct Marker;
+
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let mut world = World::new();
+        let entities = (0..10)
+            .map(|i| world.spawn((Tracked(dropped.clone()), Aligned(i))))
+            .collect::<Vec<_>>();
+
+        // Moving between archetypes must not drop anything
+        for entity in entities.iter() {
+            world.add_component(*entity, Marker).unwrap();
+        }
+        world.remove_component::<Marker>(entities[0]).unwrap();
+        assert_eq!(dropped.load(Ordering::Relaxed), 0);
+        for entity in entities.iter() {
+            let aligned = world.get_component::<Aligned>(*entity).unwrap();
+            assert_eq!(&*aligned as *const Aligned as usize % 64, 0);
+        }
+
+        world.remove_component::<Tracked>(entities[1]).unwrap();
+        world.remove(entities[2]).unwrap();
+        world
+            .add_component(entities[3], Tracked(dropped.clone()))
+            .unwrap();
+        assert_eq!(dropped.load(Ordering::Relaxed), 3);
+        assert_eq!(world.query::<&Marker>().unwrap().iter().count(), 8);
+        let mut values = world
+            .query::<&Aligned>()
+            .unwrap()
+            .iter()
+            .map(|aligned| aligned.0)
+            .collect::<Vec<_>>();
+        values.sort_unstable();
+        assert_eq!(values, vec![0, 1, 3, 4, 5, 6, 7, 8, 9]);
+
+        drop(world);
+        assert_eq!(dropped.load(Ordering::Relaxed), 11);
+    }
+
+    #[test]
+    fn registry_describes_components() {
+        use std::{alloc::Layout, mem::MaybeUninit};
+
+        #[derive(Clone, Debug, PartialEq)]
+        struct Health(u32);
+        struct Sprite(&'static str);
+
+        let mut world = World::new();
+        let health_id = world
+            .register_component::<Health>()
+            .cloneable()
+            .serialize_with(|health| format!("{:?}", health))
+            .id();
+        let tank = world.spawn((Sprite("tank"), Health(100)));
+        let sprite_id = world.components().id::<Sprite>().unwrap();
+        assert_eq!(world.components().len(), 2);
+        assert_eq!(health_id, 0);
+        assert_eq!(sprite_id, 1);
+
+        let health_info = world.components().info(health_id);
+        assert!(health_info.name().ends_with("Health"));
+        assert_eq!(health_info.layout(), Layout::new::<Health>());
+        assert!(health_info.drop().is_none());
+        let sprite_info = world.components().info(sprite_id);
+        assert!(sprite_info.clone_fn().is_none());
+        assert!(!sprite_info.is_serializable());
+
+        // Cloning through the registry doesn't need to know the type
+        let health = world.get_component::<Health>(tank).unwrap();
+        let mut copy = MaybeUninit::<Health>::uninit();
+        unsafe {
+            health_info.clone_fn().unwrap()(
+                &*health as *const Health as *const u8,
+                copy.as_mut_ptr() as *mut u8,
+            );
+            assert_eq!(copy.assume_init(), Health(100));
+        }
+        drop(health);
+
+        let mut components = world.inspect(tank).unwrap();
+        components.sort_unstable();
+        assert_eq!(components.len(), 2);
+        assert!(components[0].0.ends_with("Health"));
+        assert_eq!(components[0].1.as_deref(), Some("Health(100)"));
+        assert!(components[1].0.ends_with("Sprite"));
+        assert_eq!(components[1].1, None);
+        let _guard = world.get_component_mut::<Health>(tank).unwrap();
+        assert!(world.inspect(tank).is_err());
+    }
+
+    #[test]
+    fn sparse_components_skip_archetype_moves() {
+        use crate::ecs::queries::query_filters::{With, Without};
+
+        struct Health(u32);
+        struct Sprite(&'static str);
+        #[derive(Debug, PartialEq)]
+        struct Stunned(u32);
+
+        let mut world = World::new();
+        world.register_component::<Stunned>().sparse_set();
+        let tank = world.spawn((Health(100), Sprite("tank")));
+        let chopper = world.spawn((Health(50),));
+        let mine = world.spawn((Sprite("mine"), Stunned(9)));
+        let archetypes = world.archetypes().len();
+
+        world.add_component(tank, Stunned(1)).unwrap();
+        world.add_component(chopper, Stunned(2)).unwrap();
+        world.remove_component::<Stunned>(chopper).unwrap();
+        world.add_component(chopper, Stunned(3)).unwrap();
+        assert_eq!(world.archetypes().len(), archetypes);
+        assert_eq!(*world.get_component::<Stunned>(tank).unwrap(), Stunned(1));
+        assert!(world.has_component::<Stunned>(mine));
+        assert!(world.remove_component::<Stunned>(chopper).is_ok());
+        assert!(world.remove_component::<Stunned>(chopper).is_err());
+        assert_eq!(
+            world.removed::<Stunned>().collect::<Vec<_>>(),
+            vec![chopper, chopper]
+        );
+
+        // Sparse components mix with table ones, across archetypes
+        for (mut health, stunned) in world.query::<(&mut Health, &Stunned)>().unwrap().iter() {
+            health.0 -= stunned.0;
+        }
+        for mut stunned in world.query::<&mut Stunned>().unwrap().iter() {
+            stunned.0 += 10;
+        }
+        let mut stunned = world
+            .query::<(Entity, Option<&Stunned>)>()
+            .unwrap()
+            .iter()
+            .map(|(entity, stunned)| (entity, stunned.map(|stunned| stunned.0)))
+            .collect::<Vec<_>>();
+        stunned.sort_unstable();
+        assert_eq!(
+            stunned,
+            vec![(tank, Some(11)), (chopper, None), (mine, Some(19))]
+        );
+        assert_eq!(world.get_component::<Health>(tank).unwrap().0, 99);
+        assert_eq!(world.get_component::<Health>(chopper).unwrap().0, 50);
+        let unstunned = world
+            .query_filtered::<Entity, Without<Stunned>>()
+            .unwrap()
+            .iter()
+            .collect::<Vec<_>>();
+        assert_eq!(unstunned, vec![chopper]);
+        let sprites = world
+            .query_filtered::<&Sprite, (With<Stunned>, With<Health>)>()
+            .unwrap()
+            .iter()
+            .map(|sprite| sprite.0)
+            .collect::<Vec<_>>();
+        assert_eq!(sprites, vec!["tank"]);
+        assert!(world.query::<(&mut Stunned, &Stunned)>().is_err());
+
+        world.remove(tank).unwrap();
+        assert_eq!(world.query::<&Stunned>().unwrap().iter().count(), 1);
+        let reused = world.spawn((Health(1),));
+        assert_eq!(reused.index, tank.index);
+        assert!(!world.has_component::<Stunned>(reused));
+    }
+
+    #[test]
+    fn tags_are_not_stored() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        use crate::ecs::queries::query_filters::{Added, Changed, Or, With, Without};
+
+        struct Health(u32);
+        struct Player;
+        #[derive(Debug)]
+        struct Enemy;
+        struct Dropped;
+        impl Drop for Dropped {
+            fn drop(&mut self) {}
+        }
+
+        let mut world = World::new();
+        let tagged = Arc::new(AtomicUsize::new(0));
+        let on_add = tagged.clone();
+        world.component_hooks::<Player>().on_add(move |_, _| {
+            on_add.fetch_add(1, Ordering::Relaxed);
+        });
+        world
+            .register_component::<Enemy>()
+            .serialize_with(|enemy| format!("{:?}", enemy));
+        let link = world.spawn((Health(3), Player));
+        let moblin = world.spawn((Health(1), Enemy, Dropped));
+        let tree = world.spawn((Player, Enemy));
+
+        // Only non-tags get columns, zero-sized components with drop logic included
+        let columns = |world: &World, entity| {
+            let location = world.entities().location(entity).unwrap();
+            let archetype = world.get_archetype(location.archetype_id);
+            archetype
+                .component_ids()
+                .iter()
+                .filter(|id| archetype.get_column(**id).is_some())
+                .count()
+        };
+        assert_eq!(columns(&world, link), 1);
+        assert_eq!(columns(&world, moblin), 2);
+        assert_eq!(columns(&world, tree), 0);
+        assert_eq!(tagged.load(Ordering::Relaxed), 2);
+
+        assert!(world.has_component::<Player>(link));
+        assert!(world.get_component::<Enemy>(tree).is_some());
+        assert!(world.get_component_mut::<Player>(tree).is_some());
+        assert!(world.get_component::<Enemy>(link).is_none());
+        assert_eq!(world.query::<&Player>().unwrap().iter().count(), 2);
+        assert_eq!(world.query::<&mut Enemy>().unwrap().iter().count(), 2);
+        let players = world
+            .query_filtered::<&Health, With<Player>>()
+            .unwrap()
+            .iter()
+            .map(|health| health.0)
+            .collect::<Vec<_>>();
+        assert_eq!(players, vec![3]);
+        let mut tags = world
+            .query_filtered::<(Entity, Option<&Enemy>), Without<Health>>()
+            .unwrap()
+            .iter()
+            .map(|(entity, enemy)| (entity, enemy.is_some()))
+            .collect::<Vec<_>>();
+        tags.sort_unstable();
+        assert_eq!(tags, vec![(tree, true)]);
+        assert!(world
+            .inspect(tree)
+            .unwrap()
+            .iter()
+            .any(|(name, value)| name.ends_with("Enemy") && value.as_deref() == Some("Enemy")));
+
+        world.add_component(link, Enemy).unwrap();
+        world.add_component(link, Enemy).unwrap();
+        world.remove_component::<Player>(link).unwrap();
+        assert!(world.has_component::<Enemy>(link));
+        assert!(!world.has_component::<Player>(link));
+        assert_eq!(world.get_component::<Health>(link).unwrap().0, 3);
+        assert_eq!(world.removed::<Player>().collect::<Vec<_>>(), vec![link]);
+        world.add_component(moblin, Player).unwrap();
+        assert_eq!(tagged.load(Ordering::Relaxed), 3);
+
+        // Tags have no ticks, so tick filters on them are rejected rather than never matching
+        let untracked = |error: Option<EcsError>| {
+            matches!(
+                error,
+                Some(EcsError::QueryErr(FetchError::UntrackedTag(name))) if name.ends_with("Player")
+            )
+        };
+        assert!(untracked(
+            world.query_filtered::<Entity, Added<Player>>().err()
+        ));
+        assert!(untracked(
+            world
+                .query_filtered::<Entity, Or<(Changed<Health>, Changed<Player>)>>()
+                .err()
+        ));
+        assert!(world.query_filtered::<Entity, Added<Health>>().is_ok());
+        assert!(world.query_filtered::<Entity, Changed<Dropped>>().is_ok());
+    }
+
+    #[test]
+    fn bundles_migrate_once() {
+        use std::sync::Arc;
+
+        #[derive(Debug, PartialEq)]
+        struct Position(i32, i32);
+        #[derive(Debug, PartialEq)]
+        struct Velocity(i32, i32);
+        #[derive(Debug, PartialEq)]
+        struct Stunned(u32);
+        struct Frozen;
+
+        let mut world = World::new();
+        world.register_component::<Stunned>().sparse_set();
+        let drops = Arc::new(());
+        let bullet = world.spawn((Position(0, 0),));
+        let archetypes = world.archetypes().len();
+
+        // Only the final archetype gets created, none for the steps in between
+        world
+            .insert_bundle(
+                bullet,
+                (
+                    Velocity(1, 2),
+                    drops.clone(),
+                    Stunned(3),
+                    Frozen,
+                    Position(5, 5),
+                ),
+            )
+            .unwrap();
+        assert_eq!(world.archetypes().len(), archetypes + 1);
+        assert_eq!(
+            *world.get_component::<Position>(bullet).unwrap(),
+            Position(5, 5)
+        );
+        assert_eq!(*world.get_component::<Stunned>(bullet).unwrap(), Stunned(3));
+        assert!(world.has_component::<Frozen>(bullet));
+        assert_eq!(Arc::strong_count(&drops), 2);
+
+        // Nothing is removed if any component is missing
+        assert!(world.remove_bundle::<(Velocity, String)>(bullet).is_err());
+        assert!(world.has_component::<Velocity>(bullet));
+
+        let (velocity, stunned, _frozen, arc) = world
+            .remove_bundle::<(Velocity, Stunned, Frozen, Arc<()>)>(bullet)
+            .unwrap();
+        assert_eq!((velocity, stunned), (Velocity(1, 2), Stunned(3)));
+        assert_eq!(Arc::strong_count(&drops), 2);
+        drop(arc);
+        assert_eq!(Arc::strong_count(&drops), 1);
+        assert_eq!(world.archetypes().len(), archetypes + 1);
+        assert_eq!(
+            *world.get_component::<Position>(bullet).unwrap(),
+            Position(5, 5)
+        );
+        assert!(!world.has_component::<Velocity>(bullet));
+        assert!(!world.has_component::<Stunned>(bullet));
+        assert!(!world.has_component::<Frozen>(bullet));
+        assert_eq!(
+            world.removed::<Velocity>().collect::<Vec<_>>(),
+            vec![bullet]
+        );
+    }
+
+    #[test]
+    fn removed_components_are_returned() {
+        use std::sync::Arc;
+
+        #[derive(Debug, PartialEq)]
+        struct Weapon(&'static str, Arc<()>);
+        #[derive(Debug, PartialEq)]
+        struct Stunned(u32);
+        #[derive(Debug, PartialEq)]
+        struct Wrecked;
+
+        let mut world = World::new();
+        world.register_component::<Stunned>().sparse_set();
+        let drops = Arc::new(());
+        let tank = world.spawn((Weapon("cannon", drops.clone()), Stunned(2), Wrecked));
+        let other = world.spawn((Weapon("mortar", drops.clone()),));
+
+        let weapon = world.take_component::<Weapon>(tank).unwrap();
+        assert_eq!(weapon.0, "cannon");
+        assert_eq!(Arc::strong_count(&drops), 3);
+        let pickup = world.spawn((weapon,));
+        assert_eq!(world.get_component::<Weapon>(pickup).unwrap().0, "cannon");
+        assert_eq!(world.get_component::<Weapon>(other).unwrap().0, "mortar");
+        assert!(world.take_component::<Weapon>(tank).is_err());
+        assert_eq!(world.removed::<Weapon>().collect::<Vec<_>>(), vec![tank]);
+
+        assert_eq!(world.remove_component::<Stunned>(tank).unwrap(), Stunned(2));
+        assert_eq!(world.remove_component::<Wrecked>(tank).unwrap(), Wrecked);
+        assert!(!world.has_component::<Stunned>(tank));
+        assert!(!world.has_component::<Wrecked>(tank));
+
+        world.remove(pickup).unwrap();
+        assert_eq!(Arc::strong_count(&drops), 2);
+    }
+
+    #[test]
+    fn entity_handles_track_their_location() {
+        #[derive(Debug, PartialEq)]
+        struct Health(u32);
+        struct Sprite(&'static str);
+        #[derive(Debug, PartialEq)]
+        struct Stunned(u32);
+
+        let mut world = World::new();
+        world.register_component::<Stunned>().sparse_set();
+        let tank = world.spawn((Health(10),));
+        let chopper = world.spawn((Health(20),));
+
+        let mut handle = world.entity_mut(tank);
+        handle
+            .insert(Health(100))
+            .insert(Sprite("tank"))
+            .insert(Stunned(1));
+        assert_eq!(handle.id(), tank);
+        assert!(handle.contains::<Sprite>());
+        assert_eq!(handle.archetype().component_ids().len(), 2);
+        handle.get_mut::<Health>().unwrap().0 -= 1;
+        assert_eq!(handle.remove::<Stunned>(), Some(Stunned(1)));
+        assert_eq!(handle.remove::<Stunned>(), None);
+        assert_eq!(handle.get::<Sprite>().unwrap().0, "tank");
+        assert_eq!(handle.location(), world.entities().location(tank).unwrap());
+
+        // Moving the tank out of its archetype moved the chopper within it
+        let handle = world.entity(chopper);
+        assert_eq!(*handle.get::<Health>().unwrap(), Health(20));
+        assert!(!handle.contains::<Sprite>());
+        assert_eq!(*world.entity(tank).get::<Health>().unwrap(), Health(99));
+
+        world.entity_mut(tank).despawn();
+        assert!(world.get_entity(tank).is_none());
+        assert!(world.get_entity_mut(chopper).is_some());
+    }
+
+    #[test]
+    fn bundles_insert_into_entities_that_are_not_last() {
+        #[derive(Debug, PartialEq)]
+        struct Position(i32, i32);
+        #[derive(Debug, PartialEq)]
+        struct Stunned(u32);
+        struct Frozen;
+
+        let mut world = World::new();
+        world.register_component::<Stunned>().sparse_set();
+        let first = world.spawn((Position(1, 1),));
+        let second = world.spawn((Position(2, 2),));
+        let third = world.spawn((Position(3, 3),));
+
+        // Sparse components leave the entity where it is
+        world.insert_bundle(first, (Stunned(1),)).unwrap();
+        world.insert_bundle(second, (Position(4, 4),)).unwrap();
+        world.insert_bundle(second, (Stunned(2), Frozen)).unwrap();
+        assert_eq!(*world.get_component::<Stunned>(first).unwrap(), Stunned(1));
+        assert_eq!(*world.get_component::<Stunned>(second).unwrap(), Stunned(2));
+        assert!(world.has_component::<Frozen>(second));
+        assert!(!world.has_component::<Frozen>(first));
+        let positions =
+            [first, second, third].map(|entity| world.get_component::<Position>(entity).unwrap().0);
+        assert_eq!(positions, [1, 4, 3]);
+    }
+
+    #[test]
+    fn stale_entities_dont_reach_reused_indices() {
+        let mut world = World::new();
+        let old = world.spawn((1u32, String::from("old")));
+        world.remove(old).unwrap();
+        let new = world.spawn((2u32, String::from("new")));
+        assert_eq!(old.index, new.index);
+
+        assert!(!world.has_component::<u32>(old));
+        assert!(world.remove_component::<String>(old).is_err());
+        assert!(world.take_component::<u32>(old).is_err());
+        assert!(world.add_component(old, true).is_err());
+        assert!(!world.has_component::<bool>(new));
+        assert_eq!(*world.get_component::<u32>(new).unwrap(), 2);
+        assert_eq!(*world.get_component::<String>(new).unwrap(), "new");
+    }
+
+    #[test]
+    fn non_send_resources_stay_on_their_thread() {
+        use std::{cell::Cell, rc::Rc, thread};
+
+        use crate::ecs::resources::{NonSend, NonSendMut, Res};
+
+        struct Textures {
+            loaded: Rc<Cell<u32>>,
+            thread: thread::ThreadId,
+        }
+        struct Gravity(f32);
+
+        fn load_textures(textures: NonSendMut<Textures>) {
+            assert_eq!(thread::current().id(), textures.thread);
+            textures.loaded.set(textures.loaded.get() + 1);
+        }
+
+        fn count_textures(textures: NonSend<Textures>, gravity: Res<Gravity>) {
+            assert_eq!(thread::current().id(), textures.thread);
+            assert_eq!(gravity.0, 9.8);
+        }
+
+        fn fall(gravity: Res<Gravity>) {
+            assert_eq!(gravity.0, 9.8);
+        }
+
+        let mut world = World::new();
+        let loaded = Rc::new(Cell::new(0));
+        world.insert_non_send_resource(Textures {
+            loaded: loaded.clone(),
+            thread: thread::current().id(),
+        });
+        world.insert_resource(Gravity(9.8));
+        // Non-send systems share a batch with the others, but stay on this thread
+        world.add_system(fall);
+        world.add_system(load_textures);
+        world.add_system(fall);
+        world.add_system(count_textures);
+        world.update(0.016).unwrap();
+        world.update(0.016).unwrap();
+        assert_eq!(loaded.get(), 2);
+
+        // Other threads can't touch them, not even through a shared world
+        let world_ref = &world;
+        let result = thread::scope(|scope| {
+            scope
+                .spawn(|| world_ref.non_send_resource::<Textures>().is_ok())
+                .join()
+        });
+        assert!(result.is_err());
+
+        assert!(world.non_send_resource::<Gravity>().is_err());
+        let textures = world.remove_non_send_resource::<Textures>().unwrap();
+        assert_eq!(textures.loaded.get(), 2);
+        assert!(world.non_send_resource_mut::<Textures>().is_err());
+    }
+
+    #[test]
+    fn commands_applied_by_update_count_as_added() {
+        use crate::ecs::commands::Commands;
+        use crate::ecs::queries::query_filters::{Added, Changed};
+
+        struct Health(u32);
+        struct Spawner(u32);
+
+        fn spawn_enemies(mut query: Query<Entity, Added<Spawner>>, mut commands: Commands) {
+            for _ in query.iter() {
+                commands.spawn((Health(10),));
+            }
+        }
+
+        let mut world = World::new();
+        world.add_system(spawn_enemies);
+        world.spawn((Spawner(1), Health(1)));
+        world.update(0.016).unwrap();
+        assert_eq!(
+            world
+                .query_filtered::<&Health, Added<Health>>()
+                .unwrap()
+                .iter()
+                .map(|health| health.0)
+                .collect::<Vec<_>>(),
+            vec![10]
+        );
+        assert_eq!(
+            world
+                .query_filtered::<Entity, Changed<Health>>()
+                .unwrap()
+                .iter()
+                .count(),
+            1
+        );
+
+        world.update(0.016).unwrap();
+        assert_eq!(
+            world
+                .query_filtered::<Entity, Added<Health>>()
+                .unwrap()
+                .iter()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn zero_sized_components_are_tracked_when_stored() {
+        use crate::ecs::queries::query_filters::{Added, Changed};
+        use crate::ecs::resources::ResMut;
+
+        struct Health(u32);
+        struct Stunned;
+        struct Burning;
+        impl Drop for Burning {
+            fn drop(&mut self) {}
+        }
+        #[derive(Default)]
+        struct Seen(Vec<(usize, usize)>);
+
+        fn watch(
+            mut stunned: Query<Entity, Added<Stunned>>,
+            mut burning: Query<Entity, Changed<Burning>>,
+            mut seen: ResMut<Seen>,
+        ) {
+            let counts = (stunned.iter().count(), burning.iter().count());
+            seen.0.push(counts);
+        }
+
+        let mut world = World::new();
+        world.register_component::<Stunned>().sparse_set();
+        world.insert_resource(Seen::default());
+        world.add_system(watch);
+        let knight = world.spawn((Health(3), Burning));
+        world.update(0.016).unwrap();
+        world.update(0.016).unwrap();
+
+        // Sparse zero-sized components have ticks, as do zero-sized ones with drop logic
+        world.add_component(knight, Stunned).unwrap();
+        world.update(0.016).unwrap();
+        *world.get_component_mut::<Burning>(knight).unwrap() = Burning;
+        world.update(0.016).unwrap();
+        for mut burning in world.query::<&mut Burning>().unwrap().iter() {
+            *burning = Burning;
+        }
+        world.update(0.016).unwrap();
+        assert_eq!(
+            world.resource::<Seen>().unwrap().0,
+            vec![(0, 1), (0, 0), (1, 0), (0, 1), (0, 1)]
+        );
+    }
+}
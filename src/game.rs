@@ -1,3 +1,4 @@
+use log::error;
 use sdl2::event::Event;
 use sdl2::image::Sdl2ImageContext;
 use sdl2::keyboard::Keycode;
@@ -7,20 +8,27 @@ use sdl2::video::{Window, WindowContext};
 use sdl2::{image, EventPump};
 use std::time::{Duration, Instant};
 
+use crate::ecs::world::World;
+
 pub struct GameConfig {
+    #[allow(dead_code)]
     target_fps: u32,
     target_frame_duration: Duration,
+    #[allow(dead_code)]
     game_size: (u32, u32),
 }
 
 pub struct Game {
     event_pump: EventPump,
     canvas: Canvas<Window>,
+    #[allow(dead_code)]
     texture_creator: TextureCreator<WindowContext>,
-    img_ctx: Sdl2ImageContext,
+    /// Kept alive so SDL_image stays initialized.
+    _img_ctx: Sdl2ImageContext,
     prev_frame: Option<Instant>,
     is_running: bool,
     game_config: GameConfig,
+    world: World,
 }
 
 impl Game {
@@ -59,7 +67,7 @@ impl Game {
         Self {
             canvas,
             texture_creator,
-            img_ctx,
+            _img_ctx: img_ctx,
             event_pump,
             is_running: true,
             prev_frame: None,
@@ -68,6 +76,7 @@ impl Game {
                 game_size,
                 target_frame_duration: Duration::from_millis(1000 / target_fps as u64),
             },
+            world: World::new(),
         }
     }
 
@@ -102,10 +111,10 @@ impl Game {
         let elapsed = self.prev_frame.unwrap().elapsed();
         let dt = elapsed.as_secs_f32();
 
-        // TODO:
-        // movement_system.update();
-        // movement_system.update();
-        // movement_system.update();
+        // Movement, collision and the rest of the systems are registered in the world
+        if let Err(err) = self.world.update(dt) {
+            error!("World update failed: {}", err);
+        }
 
         if elapsed < self.game_config.target_frame_duration {
             std::thread::sleep(self.game_config.target_frame_duration - elapsed);